@@ -1,26 +1,63 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty,
-    Env, MessageInfo, Order, Reply, Response, StdResult, SubMsg, Uint128, Uint256, WasmMsg,
+    from_json, to_json_binary, to_json_vec, Addr, BankMsg, Binary, BlockInfo, Coin, ContractResult,
+    CosmosMsg, Deps, DepsMut, Empty, Env, Event, MessageInfo, Order, OverflowError,
+    OverflowOperation, QueryRequest, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    SystemResult, Uint128, Uint256, WasmMsg, WasmQuery,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw20::{Cw20Contract, Cw20ExecuteMsg, Cw20ReceiveMsg};
 use cw721::{Cw721ExecuteMsg, Cw721QueryMsg, Cw721ReceiveMsg, OwnerOfResponse};
+use cw_storage_plus::{Bound, Map};
+use cw_utils::{may_pay, must_pay, Expiration};
+use semver::Version;
+use serde::Deserialize;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg, ReceiveNftMsg};
+use crate::msg::{
+    AssetAmount, CollectionStatsResponse, CurrencyStats, EnrichedListing, ExecuteMsg,
+    FailureReason, HighestOfferResponse, InstantiateMsg, MigrateMsg, NftMetadata, NftRef,
+    NftTransfer, PageResponse, PortfolioResponse, QueryMsg, ReceiveMsg, ReceiveNftMsg,
+    RingParticipant, Settlement, SimulationResponse, SolvencyResponse, TradePreferencesMsg,
+};
 use crate::state::{
-    CoinType, Config, Listing, Offer, Trade, CONFIG, LISTINGS, LISTING_COUNTER, OFFERS, TRADES,
+    coin_key, listings, offers, price_key, sales, trades, AcceptancePolicy, CoinType, Config,
+    Custody, CustodyReason, DirectTrade, EscrowedNft, Listing, MixedOffer, Offer, Ring, RingLeg,
+    Sale, SaleKind, Stats, StoredListing, Swap, Trade, TradePreferences, WantedNft, ANY_COIN,
+    BALANCES, CLOSED_LISTINGS, COLLECTIONS, CONFIG, CREDITED, CUSTODY, DIRECT_TRADES, ESCROWED,
+    LISTING_COUNTER, MIXED_OFFERS, OFFERED_FUNDS, RINGS, RING_COUNTER, SALE_COUNTER, STATS,
+    STORED_LISTINGS, SWAPS, SWAP_TARGETS, VOLUME,
 };
 
 pub const CONTRACT_NAME: &str = "gecko-party-marketplace";
-pub const CONTRACT_VERSION: &str = "0.1.0";
+pub const CONTRACT_VERSION: &str = "0.2.0";
+
+pub const NATIVE_DENOM: &str = "uxion";
 
 pub const LISTING_REPLY: u64 = 1;
 pub const TRADE_REPLY: u64 = 2;
 pub const OFFER_REPLY: u64 = 3;
 
+// max refunds and returned NFTs sent in one go when a listing closes
+pub const MAX_CLOSE_BATCH: u32 = 30;
+// max NFTs bundled in a single mixed offer
+pub const MAX_MIXED_OFFER_NFTS: usize = 10;
+// max NFTs on either side of a swap
+pub const MAX_SWAP_NFTS: usize = 10;
+// seconds a trader has to deposit every offered NFT of a swap
+pub const SWAP_DEPOSIT_PERIOD: u64 = 86_400;
+// participants of a ring trade, two of them trade directly instead
+pub const MIN_RING_PARTICIPANTS: usize = 3;
+pub const MAX_RING_PARTICIPANTS: usize = 10;
+// seconds every participant of a ring has to deposit their NFT
+pub const RING_DEPOSIT_PERIOD: u64 = 86_400;
+// page size of list queries when no limit is given, and the largest allowed
+pub const DEFAULT_QUERY_LIMIT: u32 = 10;
+pub const MAX_QUERY_LIMIT: u32 = 100;
+// largest page of enriched listings, each one queries the collection twice
+pub const MAX_ENRICHED_QUERY_LIMIT: u32 = 30;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -33,15 +70,25 @@ pub fn instantiate(
     let config = Config {
         cw721_address: deps.api.addr_validate(&msg.cw721_address)?,
         cw20_address: deps.api.addr_validate(&msg.cw20_address)?,
+        push_to_wallets: msg.push_to_wallets.unwrap_or(false),
     };
 
     CONFIG.save(deps.storage, &config)?;
     LISTING_COUNTER.save(deps.storage, &0u128)?;
+    STATS.save(deps.storage, &Stats::default())?;
+    SALE_COUNTER.save(deps.storage, &0u64)?;
+
+    COLLECTIONS.save(deps.storage, config.cw721_address.to_string(), &Empty {})?;
+    for collection in msg.collections.unwrap_or_default() {
+        let collection = deps.api.addr_validate(&collection)?;
+        COLLECTIONS.save(deps.storage, collection.to_string(), &Empty {})?;
+    }
 
     Ok(Response::new()
         .add_attribute("action", "instantiate")
         .add_attribute("NFT", config.cw721_address)
-        .add_attribute("Cw20 Token", config.cw20_address))
+        .add_attribute("Cw20 Token", config.cw20_address)
+        .add_attribute("push to wallets", config.push_to_wallets.to_string()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -52,7 +99,7 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Buy { id } => execute_buy(deps, info, id),
+        ExecuteMsg::Buy { id } => execute_buy(deps, env, info, id),
         ExecuteMsg::Offer {
             target,
             offered_price,
@@ -62,26 +109,67 @@ pub fn execute(
             execute_accept_offer(deps, info, env, id, offerer)
         }
         ExecuteMsg::RejectOffer { id, offerer } => execute_reject_offer(deps, info, id, offerer),
-        ExecuteMsg::AcceptTrade { id, trader } => execute_accept_trade(deps, info, id, trader),
+        ExecuteMsg::AcceptDirectTrade {
+            collection,
+            id,
+            trader,
+        } => execute_accept_direct_trade(deps, env, info, collection, id, trader),
+        ExecuteMsg::RejectDirectTrade {
+            collection,
+            id,
+            trader,
+        } => execute_reject_direct_trade(deps, info, collection, id, trader),
+        ExecuteMsg::CancelDirectTrade { collection, id } => {
+            execute_cancel_direct_trade(deps, info, collection, id)
+        }
+        ExecuteMsg::ProposeSwap { asked_ids, offered } => {
+            execute_propose_swap(deps, env, info, asked_ids, offered)
+        }
+        ExecuteMsg::RefundSwap { id, trader } => execute_refund_swap(deps, env, info, id, trader),
+        ExecuteMsg::ProposeRing { participants } => {
+            execute_propose_ring(deps, env, info, participants)
+        }
+        ExecuteMsg::WithdrawRing { id } => execute_withdraw_ring(deps, info, id),
+        ExecuteMsg::RefundRing { id } => execute_refund_ring(deps, env, id),
+        ExecuteMsg::AcceptTrade { id, trader } => execute_accept_trade(deps, env, info, id, trader),
+        ExecuteMsg::ExpireTrades { start_after, limit } => {
+            execute_expire_trades(deps, env, start_after, limit)
+        }
         ExecuteMsg::CancelTrade { id } => execute_cancel_trade(deps, info, id),
+        ExecuteMsg::RejectTrade { id, trader } => execute_reject_trade(deps, info, id, trader),
+        ExecuteMsg::RejectAllTrades { id } => execute_reject_all_trades(deps, info, id),
         ExecuteMsg::CancelListing { id } => execute_cancel_listing(deps, info, id),
+        ExecuteMsg::SetOfferRules {
+            id,
+            min_offer,
+            auto_accept_at,
+        } => execute_set_offer_rules(deps, info, id, min_offer, auto_accept_at),
+        ExecuteMsg::SetTradePreferences { id, preferences } => {
+            execute_set_trade_preferences(deps, info, id, preferences)
+        }
+        ExecuteMsg::ProcessClosedListing { id, limit } => {
+            execute_process_closed_listing(deps, id, limit)
+        }
+        ExecuteMsg::MixedOffer { target, nfts } => execute_mixed_offer(deps, info, target, nfts),
+        ExecuteMsg::AcceptMixedOffer { id, offerer } => {
+            execute_accept_mixed_offer(deps, env, info, id, offerer)
+        }
+        ExecuteMsg::CancelMixedOffer { id } => execute_cancel_mixed_offer(deps, info, id),
+        ExecuteMsg::Withdraw { asset, amount } => execute_withdraw(deps, info, asset, amount),
         ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
         ExecuteMsg::ReceiveNft(msg) => execute_receive_nft(deps, env, info, msg),
     }
 }
 
 pub fn execute_buy(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     id: String,
 ) -> Result<Response, ContractError> {
-    let listing = LISTINGS.load(deps.storage, id.clone())?;
-
-    if Uint256::from_uint128(info.funds[0].amount) != listing.price {
-        return Err(ContractError::IncorrectPayment {
-            price: listing.price,
-        });
-    }
+    let listing = listings().load(deps.storage, id.clone())?;
+    let paid = must_pay(&info, NATIVE_DENOM)?;
+    check_payment(&listing, &CoinType::Native, Uint256::from_uint128(paid))?;
 
     let config = CONFIG.load(deps.storage)?;
 
@@ -99,23 +187,33 @@ pub fn execute_buy(
 
     let mut res = Response::new()
         .add_attribute("action", "receive_buy")
-        .add_attribute("NFT", listing.nft_id)
+        .add_attribute("NFT", listing.nft_id.clone())
         .add_attribute("seller", listing.owner.clone().into_string())
         .add_attribute("buyer", info.sender.to_string())
         .add_submessage(submsg);
 
-    let payment = CosmosMsg::Bank(BankMsg::Send {
-        to_address: listing.owner.into_string().clone(),
-        amount: coins(info.funds[0].amount.into(), "uxion"),
-    });
+    record_sale(
+        deps.storage,
+        &env,
+        &listing.nft_id,
+        &listing.owner,
+        &info.sender,
+        listing.price,
+        Some(CoinType::Native),
+        SaleKind::BuyNow,
+    )?;
 
-    LISTINGS.remove(deps.storage, id);
-    let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
-        Ok(counter.checked_sub(1u128).unwrap())
-    });
-    res = res.add_message(payment);
+    let payment = payout(
+        deps.branch(),
+        &config,
+        &listing.owner,
+        &CoinType::Native,
+        listing.price,
+    )?;
 
-    Ok(res)
+    res = res.add_messages(payment);
+
+    remove_listing(deps, &config, &id, res)
 }
 
 pub fn execute_offer(
@@ -125,74 +223,81 @@ pub fn execute_offer(
     asked_id: String,
     amount_offered: Uint256,
 ) -> Result<Response, ContractError> {
-    // check funds
-    if Uint256::from_uint128(info.funds[0].amount) != amount_offered {
+    // check funds, they are already held by the contract
+    let paid = must_pay(&info, NATIVE_DENOM)?;
+    if Uint256::from_uint128(paid) != amount_offered {
         return Err(ContractError::IncorrectPayment {
             price: amount_offered,
         });
     }
 
-    let payment: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: env.contract.address.to_string().clone(),
-        amount: vec![Coin {
-            denom: "uxion".to_string(),
-            amount: amount_offered.try_into()?,
-        }],
-    });
+    let listing = check_offer_policy(deps.storage, &asked_id, amount_offered, &CoinType::Native)?;
+
+    let config = CONFIG.load(deps.storage)?;
 
     let new_offer = Offer {
         asked_id: asked_id.clone(),
         offerer: info.sender,
-        amount_offered: amount_offered.clone(),
+        amount_offered,
         amount_type: CoinType::Native,
+        seller: listing.as_ref().map(|listing| listing.owner.clone()),
     };
 
-    OFFERS.save(
-        deps.storage,
-        (asked_id.clone(), new_offer.offerer.to_string()),
-        &new_offer,
-    )?;
+    save_offer(deps.storage, &config, &new_offer)?;
 
-    Ok(Response::new()
-        .add_attribute("action", "offer")
-        .add_attribute("NFT", asked_id)
-        .add_message(payment))
+    match listing {
+        Some(listing) if auto_accepts(&listing, amount_offered, &new_offer.amount_type) => {
+            Ok(settle_offer(deps, &env, &config, &listing, &new_offer)?
+                .add_attribute("auto accepted", "true"))
+        }
+        _ => Ok(Response::new()
+            .add_attribute("action", "offer")
+            .add_attribute("NFT", asked_id)
+            .add_attribute("amount", amount_offered.to_string())),
+    }
 }
 
 pub fn execute_accept_offer(
     deps: DepsMut,
     info: MessageInfo,
-    _env: Env,
+    env: Env,
     asked_id: String,
     offerer: String,
 ) -> Result<Response, ContractError> {
-    let offer = OFFERS.load(deps.storage, (asked_id.clone(), offerer))?;
-    let listing = LISTINGS.load(deps.storage, asked_id.clone())?;
+    let offer = offers().load(deps.storage, (asked_id.clone(), offerer))?;
+    let listing = listings().load(deps.storage, asked_id.clone())?;
     let config = CONFIG.load(deps.storage)?;
 
-    if listing.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
+    check_owner(&listing, &info.sender)?;
 
-    // payment from the contract
-    let payment: CosmosMsg = match offer.amount_type {
-        CoinType::Cw20 => {
-            let cw20 = Cw20Contract(config.cw20_address);
+    settle_offer(deps, &env, &config, &listing, &offer)
+}
 
-            // send payment to the contract
-            cw20.call(Cw20ExecuteMsg::Transfer {
-                recipient: info.sender.to_string().clone(),
-                amount: offer.amount_offered.try_into()?,
-            })?
-        }
-        CoinType::Native => CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string().clone(),
-            amount: vec![Coin {
-                denom: "uxion".to_string(),
-                amount: offer.amount_offered.try_into()?,
-            }],
-        }),
-    };
+fn settle_offer(
+    mut deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    listing: &Listing,
+    offer: &Offer,
+) -> Result<Response, ContractError> {
+    // payment from the contract
+    let payment = payout(
+        deps.branch(),
+        config,
+        &listing.owner,
+        &offer.amount_type,
+        offer.amount_offered,
+    )?;
+    record_sale(
+        deps.storage,
+        env,
+        &listing.nft_id,
+        &listing.owner,
+        &offer.offerer,
+        offer.amount_offered,
+        Some(offer.amount_type.clone()),
+        SaleKind::Offer,
+    )?;
 
     // Asked
     let submsgs: Vec<SubMsg> = vec![SubMsg::reply_on_success(
@@ -207,29 +312,24 @@ pub fn execute_accept_offer(
         OFFER_REPLY,
     )];
 
-    OFFERS.remove(
-        deps.storage,
-        (offer.asked_id.clone(), offer.offerer.to_string()),
-    );
-
-    LISTINGS.remove(deps.storage, offer.asked_id.clone());
-    let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
-        Ok(counter.checked_sub(1u128).unwrap())
-    });
+    remove_offer(deps.storage, config, offer)?;
 
-    Ok(Response::new()
+    let res = Response::new()
         .add_attribute("action", "accept offer")
-        .add_attribute("NFT", asked_id)
-        .add_message(payment)
-        .add_submessages(submsgs))
+        .add_attribute("NFT", offer.asked_id.clone())
+        .add_attribute("offerer", offer.offerer.to_string())
+        .add_messages(payment)
+        .add_submessages(submsgs);
+
+    remove_listing(deps, config, &offer.asked_id, res)
 }
 
 pub fn execute_cancel_offer(
-    deps: DepsMut,
+    mut deps: DepsMut,
     info: MessageInfo,
     asked_id: String,
 ) -> Result<Response, ContractError> {
-    let offer = OFFERS.load(deps.storage, (asked_id.clone(), info.sender.to_string()))?;
+    let offer = offers().load(deps.storage, (asked_id.clone(), info.sender.to_string()))?;
     let config = CONFIG.load(deps.storage)?;
 
     if offer.offerer != info.sender {
@@ -237,44 +337,30 @@ pub fn execute_cancel_offer(
     }
 
     // payment from the contract
-    let payment: CosmosMsg = match offer.amount_type {
-        CoinType::Cw20 => {
-            let cw20 = Cw20Contract(config.cw20_address);
-
-            // send payment to the contract
-            cw20.call(Cw20ExecuteMsg::Transfer {
-                recipient: offer.offerer.to_string().clone(),
-                amount: offer.amount_offered.try_into()?,
-            })?
-        }
-        CoinType::Native => CosmosMsg::Bank(BankMsg::Send {
-            to_address: offer.offerer.to_string().clone(),
-            amount: vec![Coin {
-                denom: "uxion".to_string(),
-                amount: offer.amount_offered.try_into()?,
-            }],
-        }),
-    };
+    let payment = payout(
+        deps.branch(),
+        &config,
+        &offer.offerer,
+        &offer.amount_type,
+        offer.amount_offered,
+    )?;
 
-    OFFERS.remove(
-        deps.storage,
-        (offer.asked_id.clone(), offer.offerer.to_string()),
-    );
+    remove_offer(deps.storage, &config, &offer)?;
 
     Ok(Response::new()
         .add_attribute("action", "cancel offer")
         .add_attribute("NFT", asked_id)
-        .add_message(payment))
+        .add_messages(payment))
 }
 
 pub fn execute_reject_offer(
-    deps: DepsMut,
+    mut deps: DepsMut,
     info: MessageInfo,
     asked_id: String,
     offerer: String,
 ) -> Result<Response, ContractError> {
-    let offer = OFFERS.load(deps.storage, (asked_id.clone(), offerer.to_string()))?;
-    let listing = LISTINGS.load(deps.storage, asked_id.clone())?;
+    let offer = offers().load(deps.storage, (asked_id.clone(), offerer.to_string()))?;
+    let listing = listings().load(deps.storage, asked_id.clone())?;
 
     let config = CONFIG.load(deps.storage)?;
 
@@ -283,51 +369,69 @@ pub fn execute_reject_offer(
     }
 
     // payment from the contract
-    let payment: CosmosMsg = match offer.amount_type {
-        CoinType::Cw20 => {
-            let cw20 = Cw20Contract(config.cw20_address);
-
-            // send payment to the contract
-            cw20.call(Cw20ExecuteMsg::Transfer {
-                recipient: offer.offerer.to_string().clone(),
-                amount: offer.amount_offered.try_into()?,
-            })?
-        }
-        CoinType::Native => CosmosMsg::Bank(BankMsg::Send {
-            to_address: offer.offerer.to_string().clone(),
-            amount: vec![Coin {
-                denom: "uxion".to_string(),
-                amount: offer.amount_offered.try_into()?,
-            }],
-        }),
-    };
+    let payment = payout(
+        deps.branch(),
+        &config,
+        &offer.offerer,
+        &offer.amount_type,
+        offer.amount_offered,
+    )?;
 
-    OFFERS.remove(
-        deps.storage,
-        (offer.asked_id.clone(), offer.offerer.to_string()),
-    );
+    remove_offer(deps.storage, &config, &offer)?;
 
     Ok(Response::new()
         .add_attribute("action", "reject offer")
         .add_attribute("NFT", asked_id)
-        .add_message(payment))
+        .add_messages(payment))
 }
 
 pub fn execute_accept_trade(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     asked_id: String,
     trader: String,
 ) -> Result<Response, ContractError> {
-    let trade = TRADES.load(deps.storage, (asked_id.clone(), trader))?;
-    let listing = LISTINGS.load(deps.storage, asked_id.clone())?;
+    let trade = match trades().may_load(deps.storage, (asked_id.clone(), trader.clone()))? {
+        Some(trade) => trade,
+        None => return accept_swap(deps, env, info, asked_id, trader),
+    };
+    let listing = listings().load(deps.storage, asked_id.clone())?;
+    check_trade(&listing, &trade, &info.sender, &env.block)?;
 
-    if listing.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let config = CONFIG.load(deps.storage)?;
+
+    settle_trade(deps, &env, &config, &listing, &trade)
+}
+
+/// Checks that `sender` owns the listing and can accept the trade on it now.
+fn check_trade(
+    listing: &Listing,
+    trade: &Trade,
+    sender: &Addr,
+    block: &BlockInfo,
+) -> Result<(), ContractError> {
+    check_owner(listing, sender)?;
+    if trade.expires.is_expired(block) {
+        return Err(ContractError::TradeExpired {});
     }
+    Ok(())
+}
 
-    let config = CONFIG.load(deps.storage)?;
+fn check_owner(listing: &Listing, sender: &Addr) -> Result<(), ContractError> {
+    if listing.owner != *sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
 
+fn settle_trade(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    listing: &Listing,
+    trade: &Trade,
+) -> Result<Response, ContractError> {
     // Asked
     let mut submsgs: Vec<SubMsg> = vec![SubMsg::reply_on_success(
         WasmMsg::Execute {
@@ -342,87 +446,210 @@ pub fn execute_accept_trade(
     )];
 
     // Offered
+    let offered_collection = trade_collection(config, trade);
     submsgs.push(SubMsg::reply_on_success(
-        WasmMsg::Execute {
-            contract_addr: config.cw721_address.to_string(),
-            msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
-                recipient: listing.owner.to_string(),
-                token_id: trade.to_trade_id.clone(),
-            })?,
-            funds: vec![],
-        },
+        nft_transfer_msg(offered_collection, &listing.owner, &trade.to_trade_id)?,
         TRADE_REPLY,
     ));
 
-    TRADES.remove(
+    trades().remove(
         deps.storage,
         (trade.asked_id.clone(), trade.trader.to_string()),
-    );
+    )?;
+    release_custody(deps.storage, offered_collection, &trade.to_trade_id);
 
-    LISTINGS.remove(deps.storage, trade.asked_id.clone());
-    let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
-        Ok(counter.checked_sub(1u128).unwrap())
-    });
+    record_sale(
+        deps.storage,
+        env,
+        &listing.nft_id,
+        &listing.owner,
+        &trade.trader,
+        Uint256::zero(),
+        None,
+        SaleKind::Swap,
+    )?;
 
-    if LISTINGS
-        .may_load(deps.storage, trade.to_trade_id.clone())?
-        .is_some()
-    {
-        LISTINGS.remove(deps.storage, trade.to_trade_id.clone());
-        let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
-            Ok(counter.checked_sub(1u128).unwrap())
-        });
+    let res = Response::new()
+        .add_attribute("action", "NFT traded")
+        .add_attribute("NFT asked", trade.asked_id.clone())
+        .add_attribute("NFT offered", trade.to_trade_id.clone())
+        .add_attribute("offered collection", offered_collection.to_string())
+        .add_submessages(submsgs);
+
+    remove_listing(deps, config, &trade.asked_id, res)
+}
+
+pub fn execute_set_offer_rules(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: String,
+    min_offer: Option<Uint256>,
+    auto_accept_at: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    let mut listing = listings().load(deps.storage, id.clone())?;
+
+    if listing.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match &mut listing.policy {
+        AcceptancePolicy::Flexible {
+            min_offer: floor, ..
+        } => *floor = min_offer,
+        AcceptancePolicy::AuctionOnly { reserve } => *reserve = min_offer,
     }
+    listing.auto_accept_at = auto_accept_at;
+    check_offer_rules(&listing)?;
+    listings().save(deps.storage, id.clone(), &listing)?;
 
+    let rule = |amount: Option<Uint256>| amount.map_or("none".to_string(), |a| a.to_string());
     Ok(Response::new()
-        .add_attribute("action", "NFT traded")
-        .add_attribute("NFT asked", trade.asked_id)
-        .add_attribute("NFT offered", trade.to_trade_id)
-        .add_submessages(submsgs))
+        .add_attribute("action", "set offer rules")
+        .add_attribute("NFT", id)
+        .add_attribute("min offer", rule(min_offer))
+        .add_attribute("auto accept at", rule(auto_accept_at)))
 }
 
-pub fn execute_cancel_listing(
+pub fn execute_set_trade_preferences(
     deps: DepsMut,
     info: MessageInfo,
     id: String,
+    preferences: Option<TradePreferencesMsg>,
 ) -> Result<Response, ContractError> {
-    let listing = LISTINGS.load(deps.storage, id.clone())?;
+    let mut listing = listings().load(deps.storage, id.clone())?;
 
     if listing.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
 
-    let config = CONFIG.load(deps.storage)?;
+    listing.preferences = preferences
+        .map(|preferences| validate_trade_preferences(deps.as_ref(), preferences))
+        .transpose()?;
+    listings().save(deps.storage, id.clone(), &listing)?;
 
-    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: config.cw721_address.to_string(),
-        msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
-            recipient: listing.owner.to_string(),
-            token_id: listing.nft_id.clone(),
+    Ok(Response::new()
+        .add_attribute("action", "set trade preferences")
+        .add_attribute("NFT", id))
+}
+
+fn validate_trade_preferences(
+    deps: Deps,
+    preferences: TradePreferencesMsg,
+) -> Result<TradePreferences, ContractError> {
+    let token_ids = preferences
+        .token_ids
+        .into_iter()
+        .map(|nft| {
+            Ok(WantedNft {
+                collection: deps.api.addr_validate(&nft.collection)?,
+                token_id: nft.token_id,
+            })
+        })
+        .collect::<StdResult<Vec<WantedNft>>>()?;
+    let collection = preferences
+        .collection
+        .map(|collection| deps.api.addr_validate(&collection))
+        .transpose()?;
+
+    Ok(TradePreferences {
+        token_ids,
+        collection,
+        traits: preferences.traits,
+        auto_accept: preferences.auto_accept,
+    })
+}
+
+// reads a token's `nft_info`, taking `token_uri` and `extension` apart so a
+// collection whose extension doesn't fit `NftMetadata` still shows its uri
+fn query_nft_metadata(
+    deps: Deps,
+    collection: &Addr,
+    token_id: &str,
+) -> StdResult<(Option<String>, Option<NftMetadata>)> {
+    #[derive(Deserialize)]
+    struct TokenUri {
+        token_uri: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct Extension {
+        extension: Option<NftMetadata>,
+    }
+
+    let request: QueryRequest<Empty> = WasmQuery::Smart {
+        contract_addr: collection.to_string(),
+        msg: to_json_binary(&Cw721QueryMsg::NftInfo {
+            token_id: token_id.to_string(),
         })?,
-        funds: vec![],
-    });
+    }
+    .into();
+    let raw = match deps.querier.raw_query(&to_json_vec(&request)?) {
+        SystemResult::Ok(ContractResult::Ok(raw)) => raw,
+        SystemResult::Ok(ContractResult::Err(err)) => return Err(StdError::generic_err(err)),
+        SystemResult::Err(err) => return Err(StdError::generic_err(err.to_string())),
+    };
+    let uri: TokenUri = from_json(&raw)?;
+    let extension = from_json::<Extension>(&raw)
+        .ok()
+        .and_then(|info| info.extension);
+    Ok((uri.token_uri, extension))
+}
 
-    LISTINGS.remove(deps.storage, id.clone());
+/// Fails unless the offered NFT is one the seller accepts in trade. Returns
+/// whether it is one of the NFTs the seller asked for by id.
+fn check_trade_preferences(
+    deps: Deps,
+    preferences: &TradePreferences,
+    collection: &Addr,
+    token_id: &str,
+) -> Result<bool, ContractError> {
+    if preferences
+        .token_ids
+        .iter()
+        .any(|nft| nft.collection == collection && nft.token_id == token_id)
+    {
+        return Ok(true);
+    }
 
-    let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
-        Ok(counter.checked_sub(1u128).unwrap())
-    });
+    let filtered = preferences.collection.is_some() || !preferences.traits.is_empty();
+    if !filtered && !preferences.token_ids.is_empty() {
+        return Err(ContractError::TradeNotWanted {});
+    }
 
-    Ok(Response::new()
-        .add_attribute("action", "cancel listing")
-        .add_attribute("NFT", listing.nft_id)
-        .add_message(msg))
+    if let Some(wanted) = &preferences.collection {
+        if wanted != collection {
+            return Err(ContractError::TradeNotWanted {});
+        }
+    }
+
+    if !preferences.traits.is_empty() {
+        // metadata that can't be read (a fractional value, say) has no traits
+        let attributes = query_nft_metadata(deps, collection, token_id)
+            .ok()
+            .and_then(|(_, extension)| extension)
+            .and_then(|metadata| metadata.attributes)
+            .unwrap_or_default();
+        let matches = preferences.traits.iter().all(|filter| {
+            attributes
+                .iter()
+                .any(|attr| attr.trait_type == filter.trait_type && attr.value == filter.value)
+        });
+        if !matches {
+            return Err(ContractError::TradeNotWanted {});
+        }
+    }
+
+    Ok(false)
 }
 
-pub fn execute_cancel_trade(
+pub fn execute_cancel_listing(
     deps: DepsMut,
     info: MessageInfo,
-    asked_id: String,
+    id: String,
 ) -> Result<Response, ContractError> {
-    let target = TRADES.load(deps.storage, (asked_id.clone(), info.sender.to_string()))?;
+    let listing = listings().load(deps.storage, id.clone())?;
 
-    if target.trader != info.sender {
+    if listing.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
 
@@ -431,82 +658,1841 @@ pub fn execute_cancel_trade(
     let msg = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: config.cw721_address.to_string(),
         msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
-            recipient: target.trader.to_string(),
-            token_id: target.to_trade_id,
+            recipient: listing.owner.to_string(),
+            token_id: listing.nft_id.clone(),
         })?,
         funds: vec![],
     });
 
-    TRADES.remove(deps.storage, (target.asked_id, target.trader.to_string()));
+    let res = Response::new()
+        .add_attribute("action", "cancel listing")
+        .add_attribute("NFT", listing.nft_id)
+        .add_message(msg);
 
-    Ok(Response::new()
-        .add_attribute("action", "cancel trade")
-        .add_attribute("NFT", asked_id)
-        .add_message(msg))
+    remove_listing(deps, &config, &id, res)
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
-    match reply.id {
-        LISTING_REPLY => Ok(Response::new().add_attribute("Operation", "NFT Listing")),
-        TRADE_REPLY => Ok(Response::new().add_attribute("Operation", "NFT Trade")),
-        OFFER_REPLY => Ok(Response::new().add_attribute("Operation", "NFT Price Offer")),
-        _ => Err(ContractError::UnrecognizedReply {}),
-    }
+pub fn execute_mixed_offer(
+    deps: DepsMut,
+    info: MessageInfo,
+    asked_id: String,
+    nfts: Vec<NftRef>,
+) -> Result<Response, ContractError> {
+    let amount = may_pay(&info, NATIVE_DENOM)?;
+    new_mixed_offer(
+        deps,
+        info.sender,
+        asked_id,
+        nfts,
+        Uint256::from_uint128(amount),
+        CoinType::Native,
+    )
 }
 
-pub fn execute_receive(
+pub fn new_mixed_offer(
     deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    cw20_receive_msg: Cw20ReceiveMsg,
+    offerer: Addr,
+    asked_id: String,
+    nfts: Vec<NftRef>,
+    amount_offered: Uint256,
+    amount_type: CoinType,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if config.cw20_address != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let listing = listings().load(deps.storage, asked_id.clone())?;
+    if !trades_allowed(&listing) {
+        return Err(ContractError::NonTradeable {});
     }
 
-    let msg: ReceiveMsg = from_json(&cw20_receive_msg.msg)?;
-    match msg {
-        ReceiveMsg::Buy { id } => receive_buy(
-            deps,
-            id,
-            cw20_receive_msg.sender,
-            cw20_receive_msg.amount,
-            info.sender,
-        ),
-        ReceiveMsg::Offer {
-            target,
-            offered_price,
-        } => receive_offer(
-            deps,
-            env,
-            target,
-            offered_price,
-            cw20_receive_msg.sender,
-            cw20_receive_msg.amount,
-            info.sender,
-        ),
+    if nfts.is_empty() || nfts.len() > MAX_MIXED_OFFER_NFTS {
+        return Err(ContractError::InvalidNftCount {
+            max: MAX_MIXED_OFFER_NFTS as u32,
+        });
     }
-}
 
-pub fn receive_buy(
-    deps: DepsMut,
-    id: String,
-    sender: String,
-    amount: Uint128,
-    cw20_address: Addr,
-) -> Result<Response, ContractError> {
-    let listing = LISTINGS.load(deps.storage, id.clone())?;
+    let key = (asked_id.clone(), offerer.to_string());
+    if MIXED_OFFERS.has(deps.storage, key.clone()) {
+        return Err(ContractError::OfferExists {});
+    }
 
-    if Uint256::from_uint128(amount) != listing.price {
-        return Err(ContractError::IncorrectPayment {
-            price: listing.price,
+    let mut escrowed: Vec<EscrowedNft> = vec![];
+    for nft in nfts {
+        let collection = deps.api.addr_validate(&nft.collection)?;
+        if !COLLECTIONS.has(deps.storage, collection.to_string()) {
+            return Err(ContractError::CollectionNotAllowed {});
+        }
+        if escrowed
+            .iter()
+            .any(|e| e.collection == collection && e.token_id == nft.token_id)
+        {
+            return Err(ContractError::DuplicateNft {});
+        }
+        escrowed.push(EscrowedNft {
+            collection,
+            token_id: nft.token_id,
+            deposited: false,
         });
     }
 
     let config = CONFIG.load(deps.storage)?;
-
+    let mixed = MixedOffer {
+        asked_id: asked_id.clone(),
+        offerer,
+        nfts: escrowed,
+        amount_offered,
+        amount_type,
+    };
+
+    MIXED_OFFERS.save(deps.storage, key, &mixed)?;
+    escrow_funds(
+        deps.storage,
+        &config,
+        &mixed.offerer,
+        &mixed.amount_type,
+        amount_offered,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "mixed offer")
+        .add_attribute("NFT", asked_id)
+        .add_attribute("offerer", mixed.offerer.to_string())
+        .add_attribute("amount", amount_offered.to_string())
+        .add_attribute("NFTs offered", mixed.nfts.len().to_string()))
+}
+
+pub fn receive_mixed_offer_deposit(
+    deps: DepsMut,
+    collection: Addr,
+    sender: String,
+    token_id: String,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let mut mixed = MIXED_OFFERS.load(deps.storage, (asked_id.clone(), sender))?;
+
+    let nft = mixed
+        .nfts
+        .iter_mut()
+        .find(|nft| nft.collection == collection && nft.token_id == token_id && !nft.deposited)
+        .ok_or(ContractError::UnexpectedNft {})?;
+    nft.deposited = true;
+
+    take_custody(
+        deps.storage,
+        &collection,
+        &token_id,
+        &mixed.offerer,
+        CustodyReason::MixedOffer {
+            asked_id: asked_id.clone(),
+        },
+    )?;
+
+    MIXED_OFFERS.save(
+        deps.storage,
+        (asked_id.clone(), mixed.offerer.to_string()),
+        &mixed,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "deposit mixed offer")
+        .add_attribute("NFT", asked_id)
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("deposited NFT", token_id))
+}
+
+pub fn execute_accept_mixed_offer(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asked_id: String,
+    offerer: String,
+) -> Result<Response, ContractError> {
+    let mixed = MIXED_OFFERS.load(deps.storage, (asked_id.clone(), offerer))?;
+    let listing = listings().load(deps.storage, asked_id.clone())?;
+
+    if listing.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if mixed.nfts.iter().any(|nft| !nft.deposited) {
+        return Err(ContractError::DepositsIncomplete {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    // Asked
+    let submsg = SubMsg::reply_on_success(
+        WasmMsg::Execute {
+            contract_addr: config.cw721_address.to_string(),
+            msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
+                recipient: mixed.offerer.to_string(),
+                token_id: mixed.asked_id.clone(),
+            })?,
+            funds: vec![],
+        },
+        OFFER_REPLY,
+    );
+
+    // Offered
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for nft in mixed.nfts.iter() {
+        msgs.push(nft_transfer_msg(
+            &nft.collection,
+            &listing.owner,
+            &nft.token_id,
+        )?);
+    }
+
+    MIXED_OFFERS.remove(
+        deps.storage,
+        (mixed.asked_id.clone(), mixed.offerer.to_string()),
+    );
+    for nft in mixed.nfts.iter() {
+        release_custody(deps.storage, &nft.collection, &nft.token_id);
+    }
+    release_funds(
+        deps.storage,
+        &config,
+        &mixed.offerer,
+        &mixed.amount_type,
+        mixed.amount_offered,
+    )?;
+    let payment = payout(
+        deps.branch(),
+        &config,
+        &listing.owner,
+        &mixed.amount_type,
+        mixed.amount_offered,
+    )?;
+    record_sale(
+        deps.storage,
+        &env,
+        &listing.nft_id,
+        &listing.owner,
+        &mixed.offerer,
+        mixed.amount_offered,
+        Some(mixed.amount_type.clone()),
+        SaleKind::MixedOffer,
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "accept mixed offer")
+        .add_attribute("NFT", asked_id.clone())
+        .add_attribute("offerer", mixed.offerer.to_string())
+        .add_submessage(submsg)
+        .add_messages(msgs)
+        .add_messages(payment);
+
+    remove_listing(deps, &config, &asked_id, res)
+}
+
+pub fn execute_cancel_mixed_offer(
+    deps: DepsMut,
+    info: MessageInfo,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let mixed = MIXED_OFFERS.load(deps.storage, (asked_id.clone(), info.sender.to_string()))?;
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel mixed offer")
+        .add_attribute("NFT", asked_id)
+        .add_messages(refund_mixed_offer(deps, &config, &mixed)?))
+}
+
+/// Removes a mixed offer, returning whatever NFTs were deposited and
+/// refunding the currency part to the offerer.
+fn refund_mixed_offer(
+    mut deps: DepsMut,
+    config: &Config,
+    mixed: &MixedOffer,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    MIXED_OFFERS.remove(
+        deps.storage,
+        (mixed.asked_id.clone(), mixed.offerer.to_string()),
+    );
+    release_funds(
+        deps.storage,
+        config,
+        &mixed.offerer,
+        &mixed.amount_type,
+        mixed.amount_offered,
+    )?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for nft in mixed.nfts.iter().filter(|nft| nft.deposited) {
+        msgs.push(release_nft(
+            deps.storage,
+            &nft.collection,
+            &mixed.offerer,
+            &nft.token_id,
+        )?);
+    }
+    msgs.extend(payout(
+        deps.branch(),
+        config,
+        &mixed.offerer,
+        &mixed.amount_type,
+        mixed.amount_offered,
+    )?);
+
+    Ok(msgs)
+}
+
+pub fn execute_process_closed_listing(
+    deps: DepsMut,
+    id: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    if !CLOSED_LISTINGS.has(deps.storage, id.clone()) {
+        return Err(ContractError::NotClosed {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(MAX_CLOSE_BATCH).min(MAX_CLOSE_BATCH);
+
+    let res = Response::new()
+        .add_attribute("action", "process closed listing")
+        .add_attribute("NFT", id.clone());
+
+    close_listing(deps, &config, &id, limit, res)
+}
+
+pub fn execute_withdraw(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset: CoinType,
+    amount: Option<Uint256>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let key = (info.sender.to_string(), asset_key(&config, &asset));
+
+    let balance = BALANCES
+        .may_load(deps.storage, key.clone())?
+        .unwrap_or_default();
+    let amount = amount.unwrap_or(balance);
+
+    if amount.is_zero() || amount > balance {
+        return Err(ContractError::InsufficientBalance { balance });
+    }
+
+    if amount == balance {
+        BALANCES.remove(deps.storage, key.clone());
+    } else {
+        BALANCES.save(deps.storage, key.clone(), &(balance - amount))?;
+    }
+    sub_from_total(deps.storage, &CREDITED, key.1, amount)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "withdraw")
+        .add_attribute("recipient", info.sender.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_message(payment_msg(&config, &info.sender, &asset, amount)?))
+}
+
+/// Takes a sold or cancelled listing off the market and starts closing it.
+fn remove_listing(
+    deps: DepsMut,
+    config: &Config,
+    id: &str,
+    res: Response,
+) -> Result<Response, ContractError> {
+    listings().remove(deps.storage, id.to_string())?;
+    release_custody(deps.storage, &config.cw721_address, id);
+    LISTING_COUNTER.update(deps.storage, |counter| -> Result<u128, ContractError> {
+        counter
+            .checked_sub(1)
+            .ok_or_else(|| OverflowError::new(OverflowOperation::Sub, counter, 1).into())
+    })?;
+
+    close_listing(deps, config, id, MAX_CLOSE_BATCH, res)
+}
+
+/// Refunds the outstanding offers and returns the escrowed trade NFTs on a
+/// listing that just closed, sending at most `limit` refunds and NFTs (or one
+/// proposal, however many NFTs it holds). Whatever is left over is flagged in
+/// `CLOSED_LISTINGS` so `ProcessClosedListing` can finish the job.
+fn close_listing(
+    mut deps: DepsMut,
+    config: &Config,
+    id: &str,
+    limit: u32,
+    mut res: Response,
+) -> Result<Response, ContractError> {
+    let limit = limit as usize;
+    let mut transfers = 0;
+
+    let refunded: Vec<Offer> = offers()
+        .prefix(id.to_string())
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, offer)| offer))
+        .collect::<StdResult<_>>()?;
+
+    transfers += refunded.len();
+    for offer in refunded.iter() {
+        remove_offer(deps.storage, config, offer)?;
+        res = res
+            .add_messages(payout(
+                deps.branch(),
+                config,
+                &offer.offerer,
+                &offer.amount_type,
+                offer.amount_offered,
+            )?)
+            .add_event(
+                Event::new("refund_offer")
+                    .add_attribute("NFT", id)
+                    .add_attribute("offerer", offer.offerer.to_string())
+                    .add_attribute("amount", offer.amount_offered.to_string()),
+            );
+    }
+
+    let returned: Vec<Trade> = trades()
+        .prefix(id.to_string())
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit - transfers)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect::<StdResult<_>>()?;
+
+    transfers += returned.len();
+    for trade in returned.iter() {
+        trades().remove(deps.storage, (id.to_string(), trade.trader.to_string()))?;
+        res = res
+            .add_message(release_nft(
+                deps.storage,
+                trade_collection(config, trade),
+                &trade.trader,
+                &trade.to_trade_id,
+            )?)
+            .add_event(
+                Event::new("return_trade_nft")
+                    .add_attribute("NFT", id)
+                    .add_attribute("trader", trade.trader.to_string())
+                    .add_attribute("returned NFT", trade.to_trade_id.clone()),
+            );
+    }
+
+    // mixed offers and swaps send back every deposited NFT, a proposal that
+    // doesn't fit in what is left of the batch waits for the next one
+    let fits = |transfers: usize, count: usize| transfers == 0 || transfers + count <= limit;
+
+    let mixed_offers: Vec<MixedOffer> = MIXED_OFFERS
+        .prefix(id.to_string())
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit - transfers)
+        .map(|item| item.map(|(_, mixed)| mixed))
+        .collect::<StdResult<_>>()?;
+
+    for mixed in mixed_offers {
+        // the NFTs and the funds
+        let count = mixed.nfts.iter().filter(|nft| nft.deposited).count() + 1;
+        if !fits(transfers, count) {
+            break;
+        }
+        transfers += count;
+        res = res
+            .add_messages(refund_mixed_offer(deps.branch(), config, &mixed)?)
+            .add_event(
+                Event::new("refund_mixed_offer")
+                    .add_attribute("NFT", id)
+                    .add_attribute("offerer", mixed.offerer.to_string())
+                    .add_attribute("amount", mixed.amount_offered.to_string()),
+            );
+    }
+
+    let swaps: Vec<String> = SWAP_TARGETS
+        .prefix(id.to_string())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit.saturating_sub(transfers))
+        .collect::<StdResult<_>>()?;
+
+    for trader in swaps {
+        let swap = load_swap(deps.storage, id.to_string(), trader)?;
+        let returns = swap_deposit_returns(&swap)?;
+        let count = returns.len().max(1);
+        if !fits(transfers, count) {
+            break;
+        }
+        transfers += count;
+        remove_swap(deps.storage, &swap);
+        res = res.add_messages(returns).add_event(
+            Event::new("refund_swap")
+                .add_attribute("NFT", id)
+                .add_attribute("trader", swap.trader.to_string())
+                .add_attribute("NFTs asked", swap.asked_ids.join(",")),
+        );
+    }
+
+    let pending = offers()
+        .prefix(id.to_string())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+        || trades()
+            .prefix(id.to_string())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some()
+        || MIXED_OFFERS
+            .prefix(id.to_string())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some()
+        || SWAP_TARGETS
+            .prefix(id.to_string())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .next()
+            .is_some();
+
+    if pending {
+        CLOSED_LISTINGS.save(deps.storage, id.to_string(), &Empty {})?;
+    } else {
+        CLOSED_LISTINGS.remove(deps.storage, id.to_string());
+    }
+
+    Ok(res.add_attribute("refunds pending", pending.to_string()))
+}
+
+/// Settles `amount` owed to `recipient`. Plain wallets are paid straight away
+/// when `push_to_wallets` is on; everyone else gets their internal balance
+/// credited and collects it with `Withdraw`.
+fn payout(
+    deps: DepsMut,
+    config: &Config,
+    recipient: &Addr,
+    amount_type: &CoinType,
+    amount: Uint256,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if amount.is_zero() {
+        return Ok(None);
+    }
+
+    if pays_to_wallet(deps.as_ref(), config, recipient) {
+        return Ok(Some(payment_msg(config, recipient, amount_type, amount)?));
+    }
+
+    let key = asset_key(config, amount_type);
+    BALANCES.update(
+        deps.storage,
+        (recipient.to_string(), key.clone()),
+        |balance| -> StdResult<Uint256> { Ok(balance.unwrap_or_default().checked_add(amount)?) },
+    )?;
+    add_to_total(deps.storage, &CREDITED, key, amount)?;
+
+    Ok(None)
+}
+
+/// Whether payouts to `recipient` go straight to their wallet.
+fn pays_to_wallet(deps: Deps, config: &Config, recipient: &Addr) -> bool {
+    config.push_to_wallets
+        && deps
+            .querier
+            .query_wasm_contract_info(recipient.to_string())
+            .is_err()
+}
+
+/// Storage key of an asset in `BALANCES`: the native denom or the cw20 address.
+fn asset_key(config: &Config, amount_type: &CoinType) -> String {
+    match amount_type {
+        CoinType::Native => NATIVE_DENOM.to_string(),
+        CoinType::Cw20 => config.cw20_address.to_string(),
+    }
+}
+
+fn check_currency(listing: &Listing, currency: &CoinType) -> Result<(), ContractError> {
+    match &listing.currency {
+        Some(accepted) if accepted != currency => Err(ContractError::CurrencyNotAccepted {}),
+        _ => Ok(()),
+    }
+}
+
+fn check_buy_now(listing: &Listing, currency: &CoinType) -> Result<(), ContractError> {
+    check_currency(listing, currency)?;
+    if !buy_now_allowed(listing) {
+        return Err(ContractError::BuyNowDisabled {});
+    }
+    Ok(())
+}
+
+/// Checks that the listing can be bought now with `paid` in `currency`.
+fn check_payment(
+    listing: &Listing,
+    currency: &CoinType,
+    paid: Uint256,
+) -> Result<(), ContractError> {
+    check_buy_now(listing, currency)?;
+    if paid != listing.price {
+        return Err(ContractError::IncorrectPayment {
+            price: listing.price,
+        });
+    }
+    Ok(())
+}
+
+fn buy_now_allowed(listing: &Listing) -> bool {
+    matches!(
+        listing.policy,
+        AcceptancePolicy::Flexible { buy_now: true, .. }
+    )
+}
+
+/// Checks an offer against the policy of the listing, if the NFT is listed,
+/// and returns the listing.
+fn check_offer_policy(
+    storage: &dyn Storage,
+    asked_id: &str,
+    amount: Uint256,
+    currency: &CoinType,
+) -> Result<Option<Listing>, ContractError> {
+    let listing = match listings().may_load(storage, asked_id.to_string())? {
+        Some(listing) => listing,
+        None => return Ok(None),
+    };
+    check_currency(&listing, currency)?;
+
+    let floor = match listing.policy {
+        AcceptancePolicy::Flexible {
+            offers: true,
+            min_offer,
+            ..
+        } => min_offer,
+        AcceptancePolicy::AuctionOnly { reserve } => reserve,
+        _ => return Err(ContractError::OffersDisabled {}),
+    };
+    match floor {
+        Some(min_offer) if priced_in(&listing, currency) && amount < min_offer => {
+            Err(ContractError::OfferTooLow { min_offer })
+        }
+        _ => Ok(Some(listing)),
+    }
+}
+
+fn auto_accepts(listing: &Listing, amount: Uint256, currency: &CoinType) -> bool {
+    priced_in(listing, currency)
+        && listing
+            .auto_accept_at
+            .is_some_and(|threshold| amount >= threshold)
+}
+
+// offer thresholds are amounts in the listing's currency, so they only apply
+// to offers in it; listings without a currency can't set them
+fn priced_in(listing: &Listing, currency: &CoinType) -> bool {
+    listing.currency.as_ref() == Some(currency)
+}
+
+fn check_offer_rules(listing: &Listing) -> Result<(), ContractError> {
+    let floor = match &listing.policy {
+        AcceptancePolicy::Flexible { min_offer, .. } => min_offer,
+        AcceptancePolicy::AuctionOnly { reserve } => reserve,
+    };
+    if listing.currency.is_none() && (floor.is_some() || listing.auto_accept_at.is_some()) {
+        return Err(ContractError::RulesNeedCurrency {});
+    }
+    Ok(())
+}
+
+fn trades_allowed(listing: &Listing) -> bool {
+    matches!(
+        listing.policy,
+        AcceptancePolicy::Flexible { trades: true, .. }
+    )
+}
+
+/// Stores a new offer and adds its funds to the escrow total. An offerer has
+/// at most one offer per NFT, so an existing one must be cancelled first.
+fn save_offer(
+    storage: &mut dyn Storage,
+    config: &Config,
+    offer: &Offer,
+) -> Result<(), ContractError> {
+    let key = (offer.asked_id.clone(), offer.offerer.to_string());
+    if offers().has(storage, key.clone()) {
+        return Err(ContractError::OfferExists {});
+    }
+
+    offers().save(storage, key, offer)?;
+    STATS.update(storage, |mut stats| -> StdResult<Stats> {
+        stats.open_offers += 1;
+        Ok(stats)
+    })?;
+    escrow_funds(
+        storage,
+        config,
+        &offer.offerer,
+        &offer.amount_type,
+        offer.amount_offered,
+    )
+}
+
+/// Removes an offer and releases its funds from the escrow total.
+fn remove_offer(
+    storage: &mut dyn Storage,
+    config: &Config,
+    offer: &Offer,
+) -> Result<(), ContractError> {
+    offers().remove(storage, (offer.asked_id.clone(), offer.offerer.to_string()))?;
+    STATS.update(storage, |mut stats| -> StdResult<Stats> {
+        stats.open_offers = stats.open_offers.saturating_sub(1);
+        Ok(stats)
+    })?;
+    release_funds(
+        storage,
+        config,
+        &offer.offerer,
+        &offer.amount_type,
+        offer.amount_offered,
+    )
+}
+
+/// Adds the funds of an offer or mixed offer to the escrow totals.
+fn escrow_funds(
+    storage: &mut dyn Storage,
+    config: &Config,
+    offerer: &Addr,
+    asset: &CoinType,
+    amount: Uint256,
+) -> Result<(), ContractError> {
+    let key = asset_key(config, asset);
+    OFFERED_FUNDS.update(
+        storage,
+        (offerer.to_string(), key.clone()),
+        |total| -> StdResult<Uint256> { Ok(total.unwrap_or_default().checked_add(amount)?) },
+    )?;
+    add_to_total(storage, &ESCROWED, key, amount)
+}
+
+/// Takes the funds of an offer or mixed offer out of the escrow totals.
+fn release_funds(
+    storage: &mut dyn Storage,
+    config: &Config,
+    offerer: &Addr,
+    asset: &CoinType,
+    amount: Uint256,
+) -> Result<(), ContractError> {
+    let key = asset_key(config, asset);
+    let left = OFFERED_FUNDS
+        .may_load(storage, (offerer.to_string(), key.clone()))?
+        .unwrap_or_default()
+        .checked_sub(amount)
+        .map_err(StdError::from)?;
+    if left.is_zero() {
+        OFFERED_FUNDS.remove(storage, (offerer.to_string(), key.clone()));
+    } else {
+        OFFERED_FUNDS.save(storage, (offerer.to_string(), key.clone()), &left)?;
+    }
+    sub_from_total(storage, &ESCROWED, key, amount)
+}
+
+/// Keeps an NFT of the collection that changed hands in the sales history
+/// and, unless it was swapped, counts it in the collection stats.
+#[allow(clippy::too_many_arguments)]
+fn record_sale(
+    storage: &mut dyn Storage,
+    env: &Env,
+    token_id: &str,
+    seller: &Addr,
+    buyer: &Addr,
+    price: Uint256,
+    currency: Option<CoinType>,
+    kind: SaleKind,
+) -> StdResult<()> {
+    if kind != SaleKind::Swap {
+        STATS.update(storage, |mut stats| -> StdResult<Stats> {
+            stats.sale_count += 1;
+            Ok(stats)
+        })?;
+    }
+    if let Some(currency) = &currency {
+        VOLUME.update(
+            storage,
+            coin_key(currency),
+            |volume| -> StdResult<Uint256> { Ok(volume.unwrap_or_default().checked_add(price)?) },
+        )?;
+    }
+
+    let id = SALE_COUNTER.update(storage, |id| -> StdResult<u64> { Ok(id + 1) })?;
+    let sale = Sale {
+        id,
+        token_id: token_id.to_string(),
+        seller: seller.clone(),
+        buyer: buyer.clone(),
+        price,
+        currency,
+        kind,
+        time: env.block.time,
+        height: env.block.height,
+    };
+    sales().save(storage, sale.id, &sale)
+}
+
+fn add_to_total(
+    storage: &mut dyn Storage,
+    totals: &Map<String, Uint256>,
+    key: String,
+    amount: Uint256,
+) -> Result<(), ContractError> {
+    totals.update(storage, key, |total| -> StdResult<Uint256> {
+        Ok(total.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+fn sub_from_total(
+    storage: &mut dyn Storage,
+    totals: &Map<String, Uint256>,
+    key: String,
+    amount: Uint256,
+) -> Result<(), ContractError> {
+    totals.update(storage, key, |total| -> StdResult<Uint256> {
+        Ok(total.unwrap_or_default().checked_sub(amount)?)
+    })?;
+    Ok(())
+}
+
+/// Pays `amount` of the given coin type out of the contract.
+fn payment_msg(
+    config: &Config,
+    recipient: &Addr,
+    amount_type: &CoinType,
+    amount: Uint256,
+) -> Result<CosmosMsg, ContractError> {
+    let payment = match amount_type {
+        CoinType::Cw20 => {
+            Cw20Contract(config.cw20_address.clone()).call(Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: amount.try_into()?,
+            })?
+        }
+        CoinType::Native => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: NATIVE_DENOM.to_string(),
+                amount: amount.try_into()?,
+            }],
+        }),
+    };
+    Ok(payment)
+}
+
+/// Records that the marketplace holds an NFT on behalf of `depositor`.
+fn take_custody(
+    storage: &mut dyn Storage,
+    collection: &Addr,
+    token_id: &str,
+    depositor: &Addr,
+    reason: CustodyReason,
+) -> Result<(), ContractError> {
+    let key = (collection.to_string(), token_id.to_string());
+    if CUSTODY.has(storage, key.clone()) {
+        return Err(ContractError::AlreadyInCustody {});
+    }
+
+    CUSTODY.save(
+        storage,
+        key,
+        &Custody {
+            collection: collection.clone(),
+            token_id: token_id.to_string(),
+            depositor: depositor.clone(),
+            reason,
+        },
+    )?;
+    Ok(())
+}
+
+fn release_custody(storage: &mut dyn Storage, collection: &Addr, token_id: &str) {
+    CUSTODY.remove(storage, (collection.to_string(), token_id.to_string()));
+}
+
+/// Releases an NFT from custody and transfers it to `recipient`.
+fn release_nft(
+    storage: &mut dyn Storage,
+    collection: &Addr,
+    recipient: &Addr,
+    token_id: &str,
+) -> StdResult<CosmosMsg> {
+    release_custody(storage, collection, token_id);
+    nft_transfer_msg(collection, recipient, token_id)
+}
+
+/// Collection the offered NFT of a trade belongs to.
+fn trade_collection<'a>(config: &'a Config, trade: &'a Trade) -> &'a Addr {
+    trade
+        .offered_collection
+        .as_ref()
+        .unwrap_or(&config.cw721_address)
+}
+
+fn nft_transfer_msg(collection: &Addr, recipient: &Addr, token_id: &str) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: collection.to_string(),
+        msg: to_json_binary(&Cw721ExecuteMsg::TransferNft {
+            recipient: recipient.to_string(),
+            token_id: token_id.to_string(),
+        })?,
+        funds: vec![],
+    }))
+}
+
+pub fn execute_cancel_trade(
+    deps: DepsMut,
+    info: MessageInfo,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let target =
+        match trades().may_load(deps.storage, (asked_id.clone(), info.sender.to_string()))? {
+            Some(target) => target,
+            None => return cancel_swap(deps, info, asked_id),
+        };
+
+    if target.trader != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    let msg = release_nft(
+        deps.storage,
+        trade_collection(&config, &target),
+        &target.trader,
+        &target.to_trade_id,
+    )?;
+
+    trades().remove(deps.storage, (target.asked_id, target.trader.to_string()))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel trade")
+        .add_attribute("NFT", asked_id)
+        .add_message(msg))
+}
+
+pub fn execute_reject_trade(
+    deps: DepsMut,
+    info: MessageInfo,
+    asked_id: String,
+    trader: String,
+) -> Result<Response, ContractError> {
+    let listing = listings().load(deps.storage, asked_id.clone())?;
+
+    if listing.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let trade = match trades().may_load(deps.storage, (asked_id.clone(), trader.clone()))? {
+        Some(trade) => trade,
+        None => {
+            let swap = load_swap(deps.storage, asked_id.clone(), trader)?;
+            remove_swap(deps.storage, &swap);
+
+            return Ok(Response::new()
+                .add_attribute("action", "reject swap")
+                .add_attribute("NFTs asked", swap.asked_ids.join(","))
+                .add_attribute("trader", swap.trader.to_string())
+                .add_messages(swap_deposit_returns(&swap)?));
+        }
+    };
+
+    let config = CONFIG.load(deps.storage)?;
+
+    let msg = release_nft(
+        deps.storage,
+        trade_collection(&config, &trade),
+        &trade.trader,
+        &trade.to_trade_id,
+    )?;
+
+    trades().remove(deps.storage, (trade.asked_id, trade.trader.to_string()))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "reject trade")
+        .add_attribute("NFT", asked_id)
+        .add_attribute("trader", trade.trader.to_string())
+        .add_message(msg))
+}
+
+pub fn execute_reject_all_trades(
+    deps: DepsMut,
+    info: MessageInfo,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let listing = listings().load(deps.storage, asked_id.clone())?;
+
+    if listing.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+
+    let rejected: Vec<Trade> = trades()
+        .prefix(asked_id.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(MAX_CLOSE_BATCH as usize)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect::<StdResult<_>>()?;
+
+    let mut res = Response::new()
+        .add_attribute("action", "reject all trades")
+        .add_attribute("NFT", asked_id.clone())
+        .add_attribute("rejected", rejected.len().to_string());
+
+    for trade in rejected {
+        trades().remove(deps.storage, (asked_id.clone(), trade.trader.to_string()))?;
+        res = res
+            .add_message(release_nft(
+                deps.storage,
+                trade_collection(&config, &trade),
+                &trade.trader,
+                &trade.to_trade_id,
+            )?)
+            .add_event(
+                Event::new("return_trade_nft")
+                    .add_attribute("NFT", asked_id.clone())
+                    .add_attribute("trader", trade.trader.to_string())
+                    .add_attribute("returned NFT", trade.to_trade_id),
+            );
+    }
+
+    // anything past the batch is left for another call
+    let remaining = trades()
+        .prefix(asked_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+
+    Ok(res.add_attribute("trades remaining", remaining.to_string()))
+}
+
+/// Returns the NFTs of expired trades to their traders, scanning at most
+/// `limit` trades after `start_after`. Anyone can call it.
+pub fn execute_expire_trades(
+    deps: DepsMut,
+    env: Env,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(MAX_CLOSE_BATCH).min(MAX_CLOSE_BATCH) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let scanned: Vec<((String, String), Trade)> = trades()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+
+    let mut res = Response::new().add_attribute("action", "expire trades");
+    let mut expired = 0u32;
+
+    for (key, trade) in scanned.iter() {
+        if !trade.expires.is_expired(&env.block) {
+            continue;
+        }
+        trades().remove(deps.storage, key.clone())?;
+        expired += 1;
+        res = res
+            .add_message(release_nft(
+                deps.storage,
+                trade_collection(&config, trade),
+                &trade.trader,
+                &trade.to_trade_id,
+            )?)
+            .add_event(
+                Event::new("return_trade_nft")
+                    .add_attribute("NFT", trade.asked_id.clone())
+                    .add_attribute("trader", trade.trader.to_string())
+                    .add_attribute("returned NFT", trade.to_trade_id.clone()),
+            );
+    }
+
+    res = res.add_attribute("expired", expired.to_string());
+
+    // where the next batch should start, if the scan was cut short
+    if let Some(((id, trader), _)) = scanned.last().filter(|_| scanned.len() == limit) {
+        res = res
+            .add_attribute("next_start_after_id", id)
+            .add_attribute("next_start_after_trader", trader);
+    }
+
+    Ok(res)
+}
+
+pub fn execute_propose_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asked_ids: Vec<String>,
+    offered: Vec<NftRef>,
+) -> Result<Response, ContractError> {
+    if asked_ids.is_empty()
+        || asked_ids.len() > MAX_SWAP_NFTS
+        || offered.is_empty()
+        || offered.len() > MAX_SWAP_NFTS
+    {
+        return Err(ContractError::InvalidNftCount {
+            max: MAX_SWAP_NFTS as u32,
+        });
+    }
+
+    // every asked NFT has to be a tradeable listing of the same owner
+    let mut owner: Option<Addr> = None;
+    for (i, asked_id) in asked_ids.iter().enumerate() {
+        if asked_ids[..i].contains(asked_id) {
+            return Err(ContractError::DuplicateNft {});
+        }
+        let listing = listings().load(deps.storage, asked_id.clone())?;
+        if !trades_allowed(&listing) {
+            return Err(ContractError::NonTradeable {});
+        }
+        if owner.get_or_insert(listing.owner.clone()) != &listing.owner {
+            return Err(ContractError::MixedOwners {});
+        }
+        // accept_trade and cancel_trade find a proposal by (id, trader), so a
+        // trader has either a trade or a swap on an NFT, never both
+        let key = (asked_id.clone(), info.sender.to_string());
+        if SWAP_TARGETS.has(deps.storage, key.clone()) || trades().has(deps.storage, key) {
+            return Err(ContractError::TradeExists {});
+        }
+    }
+
+    let mut escrowed: Vec<EscrowedNft> = vec![];
+    for nft in offered {
+        let collection = deps.api.addr_validate(&nft.collection)?;
+        if !COLLECTIONS.has(deps.storage, collection.to_string()) {
+            return Err(ContractError::CollectionNotAllowed {});
+        }
+        if escrowed
+            .iter()
+            .any(|e| e.collection == collection && e.token_id == nft.token_id)
+        {
+            return Err(ContractError::DuplicateNft {});
+        }
+        escrowed.push(EscrowedNft {
+            collection,
+            token_id: nft.token_id,
+            deposited: false,
+        });
+    }
+
+    let swap = Swap {
+        asked_ids,
+        trader: info.sender,
+        offered: escrowed,
+        deposit_deadline: env.block.time.plus_seconds(SWAP_DEPOSIT_PERIOD),
+    };
+
+    let key = swap.asked_ids[0].clone();
+    SWAPS.save(deps.storage, (key.clone(), swap.trader.to_string()), &swap)?;
+    for asked_id in swap.asked_ids.iter() {
+        SWAP_TARGETS.save(
+            deps.storage,
+            (asked_id.clone(), swap.trader.to_string()),
+            &key,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "propose swap")
+        .add_attribute("NFTs asked", swap.asked_ids.join(","))
+        .add_attribute("NFTs offered", swap.offered.len().to_string())
+        .add_attribute("trader", swap.trader.to_string())
+        .add_attribute("deposit deadline", swap.deposit_deadline.to_string()))
+}
+
+pub fn receive_swap_deposit(
+    deps: DepsMut,
+    collection: Addr,
+    sender: String,
+    token_id: String,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let mut swap = load_swap(deps.storage, asked_id, sender)?;
+
+    let nft = swap
+        .offered
+        .iter_mut()
+        .find(|nft| nft.collection == collection && nft.token_id == token_id && !nft.deposited)
+        .ok_or(ContractError::UnexpectedNft {})?;
+    nft.deposited = true;
+
+    take_custody(
+        deps.storage,
+        &collection,
+        &token_id,
+        &swap.trader,
+        CustodyReason::Swap {
+            asked_ids: swap.asked_ids.clone(),
+        },
+    )?;
+
+    SWAPS.save(
+        deps.storage,
+        (swap.asked_ids[0].clone(), swap.trader.to_string()),
+        &swap,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "deposit swap")
+        .add_attribute("NFTs asked", swap.asked_ids.join(","))
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("deposited NFT", token_id))
+}
+
+/// Settles a many-for-many swap. Offered NFTs that were not sent in must still
+/// belong to the trader and have the marketplace approved.
+fn accept_swap(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asked_id: String,
+    trader: String,
+) -> Result<Response, ContractError> {
+    let swap = load_swap(deps.storage, asked_id, trader)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let asked = check_swap(deps.as_ref(), &swap, &info.sender)?;
+
+    let mut submsgs: Vec<SubMsg> = vec![];
+    // Asked
+    for asked_id in swap.asked_ids.iter() {
+        submsgs.push(SubMsg::reply_on_success(
+            nft_transfer_msg(&config.cw721_address, &swap.trader, asked_id)?,
+            TRADE_REPLY,
+        ));
+    }
+
+    // Offered
+    for nft in swap.offered.iter() {
+        submsgs.push(SubMsg::reply_on_success(
+            nft_transfer_msg(&nft.collection, &info.sender, &nft.token_id)?,
+            TRADE_REPLY,
+        ));
+    }
+
+    remove_swap(deps.storage, &swap);
+
+    let mut res = Response::new()
+        .add_attribute("action", "NFTs swapped")
+        .add_attribute("NFTs asked", swap.asked_ids.join(","))
+        .add_attribute("trader", swap.trader.to_string())
+        .add_submessages(submsgs);
+
+    for listing in asked.iter() {
+        let asked_id = &listing.nft_id;
+        record_sale(
+            deps.storage,
+            &env,
+            &listing.nft_id,
+            &listing.owner,
+            &swap.trader,
+            Uint256::zero(),
+            None,
+            SaleKind::Swap,
+        )?;
+        res = remove_listing(deps.branch(), &config, asked_id, res)?;
+    }
+
+    Ok(res)
+}
+
+/// Checks that `sender` owns every asked listing and that every offered NFT
+/// is deposited or still held by the trader, and returns the asked listings.
+fn check_swap(deps: Deps, swap: &Swap, sender: &Addr) -> Result<Vec<Listing>, ContractError> {
+    let mut asked: Vec<Listing> = vec![];
+    for asked_id in swap.asked_ids.iter() {
+        let listing = listings().load(deps.storage, asked_id.clone())?;
+        check_owner(&listing, sender)?;
+        asked.push(listing);
+    }
+
+    for nft in swap.offered.iter().filter(|nft| !nft.deposited) {
+        let nft_owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+            nft.collection.to_string(),
+            &Cw721QueryMsg::OwnerOf {
+                token_id: nft.token_id.clone(),
+                include_expired: Some(false),
+            },
+        )?;
+        if nft_owner.owner != swap.trader {
+            return Err(ContractError::DepositsIncomplete {});
+        }
+    }
+
+    Ok(asked)
+}
+
+fn cancel_swap(
+    deps: DepsMut,
+    info: MessageInfo,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let swap = load_swap(deps.storage, asked_id, info.sender.to_string())?;
+
+    remove_swap(deps.storage, &swap);
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel swap")
+        .add_attribute("NFTs asked", swap.asked_ids.join(","))
+        .add_messages(swap_deposit_returns(&swap)?))
+}
+
+pub fn execute_refund_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asked_id: String,
+    trader: String,
+) -> Result<Response, ContractError> {
+    let swap = load_swap(deps.storage, asked_id, trader)?;
+
+    if env.block.time <= swap.deposit_deadline {
+        return Err(ContractError::SwapNotRefundable {});
+    }
+    // anyone may return a partial deposit; a swap with nothing or everything
+    // deposited is the trader's to withdraw
+    let deposited = swap.offered.iter().filter(|nft| nft.deposited).count();
+    if (deposited == 0 || deposited == swap.offered.len()) && info.sender != swap.trader {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    remove_swap(deps.storage, &swap);
+
+    Ok(Response::new()
+        .add_attribute("action", "refund swap")
+        .add_attribute("NFTs asked", swap.asked_ids.join(","))
+        .add_attribute("trader", swap.trader.to_string())
+        .add_messages(swap_deposit_returns(&swap)?))
+}
+
+/// Loads a swap through any of the NFTs it asks for.
+fn load_swap(storage: &dyn Storage, asked_id: String, trader: String) -> StdResult<Swap> {
+    let key = SWAP_TARGETS.load(storage, (asked_id, trader.clone()))?;
+    SWAPS.load(storage, (key, trader))
+}
+
+fn remove_swap(storage: &mut dyn Storage, swap: &Swap) {
+    SWAPS.remove(
+        storage,
+        (swap.asked_ids[0].clone(), swap.trader.to_string()),
+    );
+    for asked_id in swap.asked_ids.iter() {
+        SWAP_TARGETS.remove(storage, (asked_id.clone(), swap.trader.to_string()));
+    }
+    for nft in swap.offered.iter().filter(|nft| nft.deposited) {
+        release_custody(storage, &nft.collection, &nft.token_id);
+    }
+}
+
+fn swap_deposit_returns(swap: &Swap) -> StdResult<Vec<CosmosMsg>> {
+    swap.offered
+        .iter()
+        .filter(|nft| nft.deposited)
+        .map(|nft| nft_transfer_msg(&nft.collection, &swap.trader, &nft.token_id))
+        .collect()
+}
+
+/// Escrows the offered NFT for a token that is not listed, addressed to whoever
+/// owns that token right now.
+#[allow(clippy::too_many_arguments)]
+pub fn receive_direct_trade(
+    deps: DepsMut,
+    env: Env,
+    offered_collection: Addr,
+    sender: String,
+    offered_id: String,
+    collection: Option<String>,
+    asked_id: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let trader = deps.api.addr_validate(&sender)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let collection = match collection {
+        Some(collection) => deps.api.addr_validate(&collection)?,
+        None => config.cw721_address.clone(),
+    };
+    if !COLLECTIONS.has(deps.storage, collection.to_string()) {
+        return Err(ContractError::CollectionNotAllowed {});
+    }
+
+    let nft_owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+        collection.to_string(),
+        &Cw721QueryMsg::OwnerOf {
+            token_id: asked_id.clone(),
+            include_expired: Some(false),
+        },
+    )?;
+
+    // listed NFTs are traded through their listing, the others the
+    // marketplace holds are escrowed for a trade or offer
+    if nft_owner.owner == env.contract.address {
+        if collection == config.cw721_address && listings().has(deps.storage, asked_id) {
+            return Err(ContractError::NftListed {});
+        }
+        return Err(ContractError::AlreadyInCustody {});
+    }
+    let owner = deps.api.addr_validate(&nft_owner.owner)?;
+    if owner == trader {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::TradeExpired {});
+    }
+
+    let key = (collection.to_string(), asked_id.clone(), trader.to_string());
+    if DIRECT_TRADES.has(deps.storage, key.clone()) {
+        return Err(ContractError::TradeExists {});
+    }
+
+    let direct = DirectTrade {
+        collection,
+        asked_id: asked_id.clone(),
+        owner,
+        trader,
+        offered: EscrowedNft {
+            collection: offered_collection,
+            token_id: offered_id.clone(),
+            deposited: true,
+        },
+        expires,
+    };
+    DIRECT_TRADES.save(deps.storage, key, &direct)?;
+    take_custody(
+        deps.storage,
+        &direct.offered.collection,
+        &direct.offered.token_id,
+        &direct.trader,
+        CustodyReason::DirectTrade {
+            collection: direct.collection.clone(),
+            asked_id: asked_id.clone(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "new direct trade")
+        .add_attribute("Asked NFT", asked_id)
+        .add_attribute("Offered NFT", offered_id)
+        .add_attribute("owner", direct.owner.to_string())
+        .add_attribute("expires", expires.to_string()))
+}
+
+/// The owner accepts a direct trade by sending the asked NFT in.
+pub fn receive_accept_direct_trade(
+    deps: DepsMut,
+    env: Env,
+    collection: Addr,
+    sender: String,
+    asked_id: String,
+    trader: String,
+) -> Result<Response, ContractError> {
+    let direct = DIRECT_TRADES.load(
+        deps.storage,
+        (collection.to_string(), asked_id.clone(), trader),
+    )?;
+
+    if direct.owner != sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    settle_direct_trade(deps, env, direct)
+}
+
+/// The owner accepts a direct trade they approved the marketplace for.
+pub fn execute_accept_direct_trade(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: String,
+    asked_id: String,
+    trader: String,
+) -> Result<Response, ContractError> {
+    let direct = DIRECT_TRADES.load(deps.storage, (collection, asked_id.clone(), trader))?;
+
+    if direct.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let nft_owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+        direct.collection.to_string(),
+        &Cw721QueryMsg::OwnerOf {
+            token_id: asked_id,
+            include_expired: Some(false),
+        },
+    )?;
+    if nft_owner.owner != info.sender {
+        return Err(ContractError::NotNftOwner {});
+    }
+
+    settle_direct_trade(deps, env, direct)
+}
+
+fn settle_direct_trade(
+    deps: DepsMut,
+    env: Env,
+    direct: DirectTrade,
+) -> Result<Response, ContractError> {
+    if direct.expires.is_expired(&env.block) {
+        return Err(ContractError::TradeExpired {});
+    }
+
+    // the history only holds NFTs of the marketplace collection
+    let config = CONFIG.load(deps.storage)?;
+    if direct.collection == config.cw721_address {
+        record_sale(
+            deps.storage,
+            &env,
+            &direct.asked_id,
+            &direct.owner,
+            &direct.trader,
+            Uint256::zero(),
+            None,
+            SaleKind::Swap,
+        )?;
+    }
+
+    DIRECT_TRADES.remove(
+        deps.storage,
+        (
+            direct.collection.to_string(),
+            direct.asked_id.clone(),
+            direct.trader.to_string(),
+        ),
+    );
+    release_custody(
+        deps.storage,
+        &direct.offered.collection,
+        &direct.offered.token_id,
+    );
+
+    // Asked
+    let submsgs: Vec<SubMsg> = vec![
+        SubMsg::reply_on_success(
+            nft_transfer_msg(&direct.collection, &direct.trader, &direct.asked_id)?,
+            TRADE_REPLY,
+        ),
+        // Offered
+        SubMsg::reply_on_success(
+            nft_transfer_msg(
+                &direct.offered.collection,
+                &direct.owner,
+                &direct.offered.token_id,
+            )?,
+            TRADE_REPLY,
+        ),
+    ];
+
+    Ok(Response::new()
+        .add_attribute("action", "NFT traded")
+        .add_attribute("NFT asked", direct.asked_id)
+        .add_attribute("NFT offered", direct.offered.token_id)
+        .add_submessages(submsgs))
+}
+
+pub fn execute_reject_direct_trade(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    asked_id: String,
+    trader: String,
+) -> Result<Response, ContractError> {
+    let key = (collection, asked_id.clone(), trader);
+    let direct = DIRECT_TRADES.load(deps.storage, key.clone())?;
+
+    if direct.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    DIRECT_TRADES.remove(deps.storage, key);
+    release_custody(
+        deps.storage,
+        &direct.offered.collection,
+        &direct.offered.token_id,
+    );
+
+    Ok(Response::new()
+        .add_attribute("action", "reject direct trade")
+        .add_attribute("NFT", asked_id)
+        .add_message(nft_transfer_msg(
+            &direct.offered.collection,
+            &direct.trader,
+            &direct.offered.token_id,
+        )?))
+}
+
+pub fn execute_cancel_direct_trade(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: String,
+    asked_id: String,
+) -> Result<Response, ContractError> {
+    let key = (collection, asked_id.clone(), info.sender.to_string());
+    let direct = DIRECT_TRADES.load(deps.storage, key.clone())?;
+
+    DIRECT_TRADES.remove(deps.storage, key);
+    release_custody(
+        deps.storage,
+        &direct.offered.collection,
+        &direct.offered.token_id,
+    );
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel direct trade")
+        .add_attribute("NFT", asked_id)
+        .add_message(nft_transfer_msg(
+            &direct.offered.collection,
+            &direct.trader,
+            &direct.offered.token_id,
+        )?))
+}
+
+pub fn execute_propose_ring(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    participants: Vec<RingParticipant>,
+) -> Result<Response, ContractError> {
+    if participants.len() < MIN_RING_PARTICIPANTS || participants.len() > MAX_RING_PARTICIPANTS {
+        return Err(ContractError::InvalidRing {
+            min: MIN_RING_PARTICIPANTS as u32,
+            max: MAX_RING_PARTICIPANTS as u32,
+        });
+    }
+
+    let mut legs: Vec<RingLeg> = vec![];
+    for participant in participants {
+        let address = deps.api.addr_validate(&participant.address)?;
+        let collection = deps.api.addr_validate(&participant.nft.collection)?;
+        if !COLLECTIONS.has(deps.storage, collection.to_string()) {
+            return Err(ContractError::CollectionNotAllowed {});
+        }
+        if legs.iter().any(|leg| leg.participant == address) {
+            return Err(ContractError::InvalidRing {
+                min: MIN_RING_PARTICIPANTS as u32,
+                max: MAX_RING_PARTICIPANTS as u32,
+            });
+        }
+        if legs.iter().any(|leg| {
+            leg.nft.collection == collection && leg.nft.token_id == participant.nft.token_id
+        }) {
+            return Err(ContractError::DuplicateNft {});
+        }
+        legs.push(RingLeg {
+            participant: address,
+            nft: EscrowedNft {
+                collection,
+                token_id: participant.nft.token_id,
+                deposited: false,
+            },
+        });
+    }
+
+    if !legs.iter().any(|leg| leg.participant == info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let id = RING_COUNTER.may_load(deps.storage)?.unwrap_or_default() + 1;
+    RING_COUNTER.save(deps.storage, &id)?;
+
+    let ring = Ring {
+        id,
+        proposer: info.sender,
+        legs,
+        deposit_deadline: env.block.time.plus_seconds(RING_DEPOSIT_PERIOD),
+    };
+    RINGS.save(deps.storage, id, &ring)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose ring")
+        .add_attribute("ring", id.to_string())
+        .add_attribute("proposer", ring.proposer.to_string())
+        .add_attribute("participants", ring.legs.len().to_string())
+        .add_attribute("deposit deadline", ring.deposit_deadline.to_string()))
+}
+
+/// Escrows a participant's NFT; the last deposit settles the whole ring.
+pub fn receive_ring_deposit(
+    deps: DepsMut,
+    env: Env,
+    collection: Addr,
+    sender: String,
+    token_id: String,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut ring = RINGS.load(deps.storage, id)?;
+
+    if env.block.time > ring.deposit_deadline {
+        return Err(ContractError::TradeExpired {});
+    }
+
+    let leg = ring
+        .legs
+        .iter_mut()
+        .find(|leg| {
+            leg.participant == sender
+                && leg.nft.collection == collection
+                && leg.nft.token_id == token_id
+                && !leg.nft.deposited
+        })
+        .ok_or(ContractError::UnexpectedNft {})?;
+    leg.nft.deposited = true;
+    let participant = leg.participant.clone();
+
+    take_custody(
+        deps.storage,
+        &collection,
+        &token_id,
+        &participant,
+        CustodyReason::Ring { id },
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "deposit ring")
+        .add_attribute("ring", id.to_string())
+        .add_attribute("participant", participant.to_string())
+        .add_attribute("deposited NFT", token_id);
+
+    if ring.legs.iter().any(|leg| !leg.nft.deposited) {
+        RINGS.save(deps.storage, id, &ring)?;
+        return Ok(res);
+    }
+
+    // everyone has committed, hand each NFT to the next participant
+    RINGS.remove(deps.storage, id);
+    let config = CONFIG.load(deps.storage)?;
+    let mut submsgs: Vec<SubMsg> = vec![];
+    for (i, leg) in ring.legs.iter().enumerate() {
+        let recipient = &ring.legs[(i + 1) % ring.legs.len()].participant;
+        release_custody(deps.storage, &leg.nft.collection, &leg.nft.token_id);
+        if leg.nft.collection == config.cw721_address {
+            record_sale(
+                deps.storage,
+                &env,
+                &leg.nft.token_id,
+                &leg.participant,
+                recipient,
+                Uint256::zero(),
+                None,
+                SaleKind::Swap,
+            )?;
+        }
+        submsgs.push(SubMsg::reply_on_success(
+            nft_transfer_msg(&leg.nft.collection, recipient, &leg.nft.token_id)?,
+            TRADE_REPLY,
+        ));
+    }
+
+    Ok(res
+        .add_attribute("settled", "true")
+        .add_submessages(submsgs))
+}
+
+pub fn execute_withdraw_ring(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let ring = RINGS.load(deps.storage, id)?;
+
+    if !ring.legs.iter().any(|leg| leg.participant == info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "withdraw ring")
+        .add_attribute("ring", id.to_string())
+        .add_attribute("participant", info.sender.to_string())
+        .add_messages(refund_ring(deps.storage, &ring)?))
+}
+
+pub fn execute_refund_ring(deps: DepsMut, env: Env, id: u64) -> Result<Response, ContractError> {
+    let ring = RINGS.load(deps.storage, id)?;
+
+    if env.block.time <= ring.deposit_deadline {
+        return Err(ContractError::RingNotRefundable {});
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "refund ring")
+        .add_attribute("ring", id.to_string())
+        .add_messages(refund_ring(deps.storage, &ring)?))
+}
+
+/// Removes a ring and returns every deposited NFT to its participant.
+fn refund_ring(storage: &mut dyn Storage, ring: &Ring) -> StdResult<Vec<CosmosMsg>> {
+    RINGS.remove(storage, ring.id);
+    ring.legs
+        .iter()
+        .filter(|leg| leg.nft.deposited)
+        .map(|leg| {
+            release_nft(
+                storage,
+                &leg.nft.collection,
+                &leg.participant,
+                &leg.nft.token_id,
+            )
+        })
+        .collect()
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match reply.id {
+        LISTING_REPLY => Ok(Response::new().add_attribute("Operation", "NFT Listing")),
+        TRADE_REPLY => Ok(Response::new().add_attribute("Operation", "NFT Trade")),
+        OFFER_REPLY => Ok(Response::new().add_attribute("Operation", "NFT Price Offer")),
+        _ => Err(ContractError::UnrecognizedReply {}),
+    }
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.cw20_address != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let msg: ReceiveMsg = from_json(&cw20_receive_msg.msg)?;
+    match msg {
+        ReceiveMsg::Buy { id } => receive_buy(
+            deps,
+            env,
+            id,
+            cw20_receive_msg.sender,
+            cw20_receive_msg.amount,
+        ),
+        ReceiveMsg::Offer {
+            target,
+            offered_price,
+        } => receive_offer(
+            deps,
+            env,
+            target,
+            offered_price,
+            cw20_receive_msg.sender,
+            cw20_receive_msg.amount,
+        ),
+        ReceiveMsg::MixedOffer { target, nfts } => {
+            let sender = deps.api.addr_validate(&cw20_receive_msg.sender)?;
+            let amount = Uint256::from_uint128(cw20_receive_msg.amount);
+            new_mixed_offer(deps, sender, target, nfts, amount, CoinType::Cw20)
+        }
+    }
+}
+
+pub fn receive_buy(
+    mut deps: DepsMut,
+    env: Env,
+    id: String,
+    sender: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let listing = listings().load(deps.storage, id.clone())?;
+    check_payment(&listing, &CoinType::Cw20, Uint256::from_uint128(amount))?;
+
+    let config = CONFIG.load(deps.storage)?;
+
     let submsg = SubMsg::reply_on_success(
         WasmMsg::Execute {
             contract_addr: config.cw721_address.to_string(),
@@ -521,25 +2507,33 @@ pub fn receive_buy(
 
     let mut res = Response::new()
         .add_attribute("action", "receive_buy")
-        .add_attribute("NFT", listing.nft_id)
+        .add_attribute("NFT", listing.nft_id.clone())
         .add_attribute("seller", listing.owner.clone().into_string())
-        .add_attribute("buyer", sender)
+        .add_attribute("buyer", sender.clone())
         .add_submessage(submsg);
 
-    let cw20 = Cw20Contract(cw20_address);
+    record_sale(
+        deps.storage,
+        &env,
+        &listing.nft_id,
+        &listing.owner,
+        &deps.api.addr_validate(&sender)?,
+        listing.price,
+        Some(CoinType::Cw20),
+        SaleKind::BuyNow,
+    )?;
 
-    let payment = cw20.call(Cw20ExecuteMsg::Transfer {
-        recipient: listing.owner.into_string().clone(),
-        amount,
-    })?;
+    let payment = payout(
+        deps.branch(),
+        &config,
+        &listing.owner,
+        &CoinType::Cw20,
+        Uint256::from_uint128(amount),
+    )?;
 
-    LISTINGS.remove(deps.storage, id);
-    let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
-        Ok(counter.checked_sub(1u128).unwrap())
-    });
-    res = res.add_message(payment);
+    res = res.add_messages(payment);
 
-    Ok(res)
+    remove_listing(deps, &config, &id, res)
 }
 
 pub fn receive_offer(
@@ -549,47 +2543,49 @@ pub fn receive_offer(
     amount_offered: Uint256,
     sender: String,
     amount: Uint128,
-    cw20_address: Addr,
 ) -> Result<Response, ContractError> {
-    // check funds
+    // check funds, they are already held by the contract
     if Uint256::from_uint128(amount) != amount_offered {
         return Err(ContractError::IncorrectPayment {
             price: amount_offered,
         });
     }
 
+    let listing = check_offer_policy(deps.storage, &asked_id, amount_offered, &CoinType::Cw20)?;
+
+    let config = CONFIG.load(deps.storage)?;
+
     let sender_addr = deps.api.addr_validate(&sender)?;
     let new_offer = Offer {
         asked_id: asked_id.clone(),
         offerer: sender_addr.clone(),
-        amount_offered: amount_offered.clone(),
+        amount_offered,
         amount_type: CoinType::Cw20,
+        seller: listing.as_ref().map(|listing| listing.owner.clone()),
     };
 
-    OFFERS.save(
-        deps.storage,
-        (asked_id.clone(), new_offer.offerer.to_string()),
-        &new_offer,
-    )?;
-
-    let cw20 = Cw20Contract(cw20_address);
-
-    // send payment to the contract
-    let payment = cw20.call(Cw20ExecuteMsg::Transfer {
-        recipient: env.contract.address.into_string().clone(),
-        amount,
-    })?;
+    save_offer(deps.storage, &config, &new_offer)?;
 
-    Ok(Response::new().add_message(payment))
+    match listing {
+        Some(listing) if auto_accepts(&listing, amount_offered, &new_offer.amount_type) => {
+            Ok(settle_offer(deps, &env, &config, &listing, &new_offer)?
+                .add_attribute("auto accepted", "true"))
+        }
+        _ => Ok(Response::new()
+            .add_attribute("action", "offer")
+            .add_attribute("NFT", asked_id)
+            .add_attribute("amount", amount_offered.to_string())),
+    }
 }
+
 pub fn execute_receive_nft(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     receive_msg: Cw721ReceiveMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    if config.cw721_address != info.sender {
+    if !COLLECTIONS.has(deps.storage, info.sender.to_string()) {
         return Err(ContractError::Unauthorized {});
     };
 
@@ -598,32 +2594,121 @@ pub fn execute_receive_nft(
 
     let msg: ReceiveNftMsg = from_json(&receive_msg.msg)?;
     match msg {
-        ReceiveNftMsg::NewListing { price, tradeable } => {
-            receive_new_listing(deps, sender, receive_msg.token_id, price, tradeable)
+        ReceiveNftMsg::NewListing {
+            price,
+            policy,
+            preferences,
+            auto_accept_at,
+            currency,
+        } => {
+            if config.cw721_address != info.sender {
+                return Err(ContractError::Unauthorized {});
+            }
+            receive_new_listing(
+                deps,
+                sender,
+                receive_msg.token_id,
+                price,
+                policy,
+                preferences,
+                auto_accept_at,
+                currency,
+            )
+        }
+        ReceiveNftMsg::NewTrade { target, expires } => receive_new_trade(
+            deps,
+            env,
+            info.sender,
+            sender,
+            receive_msg.token_id,
+            target,
+            expires,
+        ),
+        ReceiveNftMsg::DepositMixedOffer { target } => {
+            receive_mixed_offer_deposit(deps, info.sender, sender, receive_msg.token_id, target)
         }
-        ReceiveNftMsg::NewTrade { target } => {
-            receive_new_trade(deps, sender, receive_msg.token_id, target)
+        ReceiveNftMsg::DepositSwap { target } => {
+            receive_swap_deposit(deps, info.sender, sender, receive_msg.token_id, target)
+        }
+        ReceiveNftMsg::ProposeDirectTrade {
+            collection,
+            target,
+            expires,
+        } => receive_direct_trade(
+            deps,
+            env,
+            info.sender,
+            sender,
+            receive_msg.token_id,
+            collection,
+            target,
+            expires,
+        ),
+        ReceiveNftMsg::AcceptDirectTrade { trader } => receive_accept_direct_trade(
+            deps,
+            env,
+            info.sender,
+            sender,
+            receive_msg.token_id,
+            trader,
+        ),
+        ReceiveNftMsg::DepositRing { id } => {
+            receive_ring_deposit(deps, env, info.sender, sender, receive_msg.token_id, id)
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn receive_new_listing(
     deps: DepsMut,
     sender: String,
     id: String,
     price: Uint256,
-    tradeable: bool,
+    policy: AcceptancePolicy,
+    preferences: Option<TradePreferencesMsg>,
+    auto_accept_at: Option<Uint256>,
+    currency: Option<CoinType>,
 ) -> Result<Response, ContractError> {
     let owner = deps.api.addr_validate(&sender)?;
+    let preferences = preferences
+        .map(|preferences| validate_trade_preferences(deps.as_ref(), preferences))
+        .transpose()?;
+
+    if CLOSED_LISTINGS.has(deps.storage, id.clone()) {
+        return Err(ContractError::RefundsPending {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
 
     let new_listing = Listing {
         nft_id: id.clone(),
         price,
         owner,
-        tradeable,
+        policy,
+        preferences,
+        auto_accept_at,
+        currency,
     };
+    check_offer_rules(&new_listing)?;
 
-    LISTINGS.save(deps.storage, id.clone(), &new_listing)?;
+    listings().save(deps.storage, id.clone(), &new_listing)?;
+    // offers made while the NFT was not listed now have a seller
+    let waiting: Vec<((String, String), Offer)> = offers()
+        .prefix(id.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(offerer, offer)| ((id.clone(), offerer), offer)))
+        .collect::<StdResult<_>>()?;
+    for (key, mut offer) in waiting {
+        offer.seller = Some(new_listing.owner.clone());
+        offers().save(deps.storage, key, &offer)?;
+    }
+    take_custody(
+        deps.storage,
+        &config.cw721_address,
+        &id,
+        &new_listing.owner,
+        CustodyReason::Listing {},
+    )?;
     let _ = LISTING_COUNTER.update(deps.storage, |counter: u128| -> StdResult<u128> {
         Ok(counter.checked_add(1u128).unwrap())
     });
@@ -638,259 +2723,1178 @@ pub fn receive_new_listing(
 
 pub fn receive_new_trade(
     deps: DepsMut,
+    env: Env,
+    collection: Addr,
     sender: String,
     offered_id: String,
     asked_id: String,
+    expires: Option<Expiration>,
 ) -> Result<Response, ContractError> {
+    // the NFT is already held by the marketplace at this point; its previous
+    // owner is the sender the cw721 contract authorized for SendNft
     let trader = deps.api.addr_validate(&sender)?;
-    let config = CONFIG.load(deps.storage)?;
 
-    let nft_owner: OwnerOfResponse = deps.querier.query_wasm_smart(
-        config.cw721_address.to_string(),
-        &Cw721QueryMsg::OwnerOf {
-            token_id: offered_id.clone(),
-            include_expired: Some(false),
-        },
-    )?;
+    let listing = listings().load(deps.storage, asked_id.clone())?;
 
-    if nft_owner.owner != trader.clone() {
-        return Err(ContractError::Unauthorized {});
+    if !trades_allowed(&listing) {
+        return Err(ContractError::NonTradeable {});
     }
 
-    let listing = LISTINGS.load(deps.storage, asked_id.clone())?;
+    let auto_accept = match &listing.preferences {
+        Some(preferences) => {
+            check_trade_preferences(deps.as_ref(), preferences, &collection, &offered_id)?
+                && preferences.auto_accept
+        }
+        None => false,
+    };
 
-    if !listing.tradeable {
-        return Err(ContractError::NonTradeable {});
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::TradeExpired {});
     }
 
     let new_trade = Trade {
         asked_id: asked_id.clone(),
         to_trade_id: offered_id.clone(),
+        offered_collection: Some(collection.clone()),
         trader,
+        expires,
+        seller: Some(listing.owner.clone()),
     };
 
-    TRADES.save(
+    let key = (asked_id.clone(), new_trade.trader.to_string());
+    if trades().has(deps.storage, key.clone()) || SWAP_TARGETS.has(deps.storage, key.clone()) {
+        return Err(ContractError::TradeExists {});
+    }
+
+    trades().save(deps.storage, key, &new_trade)?;
+    take_custody(
         deps.storage,
-        (asked_id.clone(), new_trade.trader.to_string()),
-        &new_trade,
+        &collection,
+        &offered_id,
+        &new_trade.trader,
+        CustodyReason::Trade {
+            asked_id: asked_id.clone(),
+        },
     )?;
 
+    // the seller asked for exactly this NFT, trade it right away
+    if auto_accept {
+        let config = CONFIG.load(deps.storage)?;
+        return Ok(settle_trade(deps, &env, &config, &listing, &new_trade)?
+            .add_attribute("auto accepted", "true"));
+    }
+
     Ok(Response::new()
         .add_attribute("action", "new trade")
         .add_attribute("Asked NFT", asked_id)
-        .add_attribute("Offered NFT", offered_id))
+        .add_attribute("Offered NFT", offered_id)
+        .add_attribute("offered collection", collection.to_string())
+        .add_attribute("expires", expires.to_string()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: Empty) -> Result<Response, ContractError> {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // only upgrades from an older version of this contract
+    let previous = get_contract_version(deps.storage)?;
+    if previous.contract != CONTRACT_NAME
+        || previous.version.parse::<Version>()? >= CONTRACT_VERSION.parse::<Version>()?
+    {
+        return Err(ContractError::CannotMigrate {
+            contract: previous.contract,
+            version: previous.version,
+        });
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.push_to_wallets = msg.push_to_wallets.unwrap_or(true);
+    CONFIG.save(deps.storage, &config)?;
+    COLLECTIONS.save(deps.storage, config.cw721_address.to_string(), &Empty {})?;
+    for collection in msg.collections.unwrap_or_default() {
+        let collection = deps.api.addr_validate(&collection)?;
+        COLLECTIONS.save(deps.storage, collection.to_string(), &Empty {})?;
+    }
+
+    // listings stored with a `tradeable` flag get the matching policy, and
+    // saving every listing again fills in its indexes. The stored value is not
+    // passed as the old one, it doesn't parse as a `Listing`
+    let stored: Vec<(String, StoredListing)> = STORED_LISTINGS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (id, listing) in stored {
+        listings().replace(
+            deps.storage,
+            id,
+            Some(&Listing {
+                nft_id: listing.nft_id,
+                price: listing.price,
+                owner: listing.owner,
+                policy: AcceptancePolicy::Flexible {
+                    buy_now: true,
+                    offers: true,
+                    min_offer: None,
+                    trades: listing.tradeable,
+                },
+                preferences: None,
+                auto_accept_at: None,
+                currency: None,
+            }),
+            None,
+        )?;
+    }
+
+    // and the same for the trade and offer indexes, once they have the seller
+    // of their listing
+    let stored_trades: Vec<((String, String), Trade)> = trades()
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (key, mut trade) in stored_trades {
+        trade.seller = listings()
+            .may_load(deps.storage, trade.asked_id.clone())?
+            .map(|listing| listing.owner);
+        trades().save(deps.storage, key, &trade)?;
+    }
+    let stored_offers: Vec<((String, String), Offer)> = offers()
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (key, offer) in stored_offers.iter() {
+        let mut offer = offer.clone();
+        offer.seller = listings()
+            .may_load(deps.storage, offer.asked_id.clone())?
+            .map(|listing| listing.owner);
+        offers().save(deps.storage, key.clone(), &offer)?;
+    }
+
+    // the first release kept no stats or sales history
+    STATS.save(
+        deps.storage,
+        &Stats {
+            open_offers: stored_offers.len() as u64,
+            sale_count: 0,
+        },
+    )?;
+    SALE_COUNTER.save(deps.storage, &0u64)?;
+
+    // the first release held the offered funds and the listed and traded NFTs
+    // without recording them
+    for (_, offer) in stored_offers {
+        escrow_funds(
+            deps.storage,
+            &config,
+            &offer.offerer,
+            &offer.amount_type,
+            offer.amount_offered,
+        )?;
+    }
+    let listed: Vec<Listing> = listings()
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, listing)| listing))
+        .collect::<StdResult<_>>()?;
+    for listing in listed {
+        take_custody(
+            deps.storage,
+            &config.cw721_address,
+            &listing.nft_id,
+            &listing.owner,
+            CustodyReason::Listing {},
+        )?;
+    }
+    let traded: Vec<Trade> = trades()
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect::<StdResult<_>>()?;
+    for trade in traded {
+        take_custody(
+            deps.storage,
+            &config.cw721_address,
+            &trade.to_trade_id,
+            &trade.trader,
+            CustodyReason::Trade {
+                asked_id: trade.asked_id,
+            },
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from", previous.version)
+        .add_attribute("to", CONTRACT_VERSION))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetListing { id } => to_json_binary(&get_listing(deps, id)?),
         QueryMsg::GetListingsBySeller {
             seller,
-            from_index,
+            start_after,
+            limit,
+        } => to_json_binary(&get_listings_seller(deps, seller, start_after, limit)?),
+        QueryMsg::GetAllListings { start_after, limit } => {
+            to_json_binary(&get_all_listings(deps, start_after, limit)?)
+        }
+        QueryMsg::GetListingsByPrice {
+            min_price,
+            max_price,
+            currency,
+            seller,
+            descending,
+            start_after,
+            limit,
+        } => to_json_binary(&get_listings_by_price(
+            deps,
+            min_price,
+            max_price,
+            currency,
+            seller,
+            descending,
+            start_after,
             limit,
-        } => to_json_binary(&get_listings_seller(deps, seller, from_index, limit)?),
-        QueryMsg::GetAllListings { from_index, limit } => {
-            to_json_binary(&get_all_listings(deps, from_index, limit)?)
+        )?),
+        QueryMsg::GetEnrichedListing { id } => to_json_binary(&get_enriched_listing(deps, id)?),
+        QueryMsg::GetEnrichedListings { start_after, limit } => {
+            to_json_binary(&get_enriched_listings(deps, start_after, limit)?)
         }
         QueryMsg::GetListingCount {} => to_json_binary(&get_listing_count(deps)?),
         QueryMsg::GetTrade { id, trader } => to_json_binary(&get_trade(deps, id, trader)?),
         QueryMsg::GetTradesByAddress {
             address,
-            from_index,
+            start_after,
             limit,
-        } => to_json_binary(&get_trades_address(deps, address, from_index, limit)?),
+        } => to_json_binary(&get_trades_address(deps, address, start_after, limit)?),
         QueryMsg::GetTradesById {
             id,
-            from_index,
+            start_after,
             limit,
-        } => to_json_binary(&get_trades_id(deps, id, from_index, limit)?),
-        QueryMsg::GetAllTrades { from_index, limit } => {
-            to_json_binary(&get_all_trades(deps, from_index, limit)?)
+        } => to_json_binary(&get_trades_id(deps, id, start_after, limit)?),
+        QueryMsg::GetAllTrades { start_after, limit } => {
+            to_json_binary(&get_all_trades(deps, start_after, limit)?)
         }
+        QueryMsg::GetTradesBySeller {
+            seller,
+            start_after,
+            limit,
+        } => to_json_binary(&get_trades_seller(deps, seller, start_after, limit)?),
         QueryMsg::GetOffer { id, offerer } => to_json_binary(&get_offer(deps, id, offerer)?),
         QueryMsg::GetOffersByAddress {
             address,
-            from_index,
+            start_after,
             limit,
-        } => to_json_binary(&get_offers_address(deps, address, from_index, limit)?),
+        } => to_json_binary(&get_offers_address(deps, address, start_after, limit)?),
         QueryMsg::GetOffersById {
             id,
-            from_index,
+            start_after,
+            limit,
+        } => to_json_binary(&get_offers_id(deps, id, start_after, limit)?),
+        QueryMsg::GetAllOffers { start_after, limit } => {
+            to_json_binary(&get_all_offers(deps, start_after, limit)?)
+        }
+        QueryMsg::GetOffersBySeller {
+            seller,
+            start_after,
+            limit,
+        } => to_json_binary(&get_offers_seller(deps, seller, start_after, limit)?),
+        QueryMsg::HighestOffer { id } => to_json_binary(&get_highest_offer(deps, id)?),
+        QueryMsg::OffersSortedByPrice {
+            id,
+            currency,
+            start_after,
+            limit,
+        } => to_json_binary(&get_offers_by_price(
+            deps,
+            id,
+            currency,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::GetMixedOffer { id, offerer } => {
+            to_json_binary(&get_mixed_offer(deps, id, offerer)?)
+        }
+        QueryMsg::GetMixedOffersById {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&get_mixed_offers_id(deps, id, start_after, limit)?),
+        QueryMsg::GetSwap { id, trader } => to_json_binary(&get_swap(deps, id, trader)?),
+        QueryMsg::GetRing { id } => to_json_binary(&get_ring(deps, id)?),
+        QueryMsg::GetDirectTrade {
+            collection,
+            id,
+            trader,
+        } => to_json_binary(&get_direct_trade(deps, collection, id, trader)?),
+        QueryMsg::GetDirectTradesByToken {
+            collection,
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&get_direct_trades_token(
+            deps,
+            collection,
+            id,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::GetCustody { collection, id } => {
+            to_json_binary(&get_custody(deps, collection, id)?)
+        }
+        QueryMsg::GetAllCustody { start_after, limit } => {
+            to_json_binary(&get_all_custody(deps, start_after, limit)?)
+        }
+        QueryMsg::GetSalesByToken {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&get_sales_token(deps, id, start_after, limit)?),
+        QueryMsg::GetSalesByAddress {
+            address,
+            start_after,
             limit,
-        } => to_json_binary(&get_offers_id(deps, id, from_index, limit)?),
-        QueryMsg::GetAllOffers { from_index, limit } => {
-            to_json_binary(&get_all_offers(deps, from_index, limit)?)
+        } => to_json_binary(&get_sales_address(deps, address, start_after, limit)?),
+        QueryMsg::GetAllSales { start_after, limit } => {
+            to_json_binary(&get_all_sales(deps, start_after, limit)?)
+        }
+        QueryMsg::LastSale { id } => to_json_binary(&get_last_sale(deps, id)?),
+        QueryMsg::PriceHistory { id, limit } => {
+            to_json_binary(&get_price_history(deps, id, limit)?)
+        }
+        QueryMsg::Portfolio { address } => to_json_binary(&get_portfolio(deps, address)?),
+        QueryMsg::CollectionStats {} => to_json_binary(&get_collection_stats(deps)?),
+        QueryMsg::SimulateBuy { id, buyer, funds } => {
+            to_json_binary(&simulate_buy(deps, id, buyer, funds)?)
+        }
+        QueryMsg::SimulateAcceptOffer {
+            id,
+            offerer,
+            seller,
+        } => to_json_binary(&simulate_accept_offer(deps, id, offerer, seller)?),
+        QueryMsg::SimulateAcceptTrade { id, trader, seller } => {
+            to_json_binary(&simulate_accept_trade(deps, env, id, trader, seller)?)
         }
+        QueryMsg::Balance { address, asset } => to_json_binary(&get_balance(deps, address, asset)?),
+        QueryMsg::Solvency {} => to_json_binary(&get_solvency(deps, env)?),
     }
 }
 
 pub fn get_listing_count(deps: Deps) -> StdResult<u128> {
-    Ok(LISTING_COUNTER.load(deps.storage)?)
+    LISTING_COUNTER.load(deps.storage)
+}
+
+pub fn get_portfolio(deps: Deps, address: String) -> StdResult<PortfolioResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let limit = Some(MAX_QUERY_LIMIT);
+
+    // per currency, read from running totals so any number of offers is fine
+    let totals = |map: &Map<(String, String), Uint256>| -> StdResult<Vec<AssetAmount>> {
+        [CoinType::Native, CoinType::Cw20]
+            .into_iter()
+            .map(|asset| {
+                let amount = map
+                    .may_load(deps.storage, (address.clone(), asset_key(&config, &asset)))?
+                    .unwrap_or_default();
+                Ok(AssetAmount { asset, amount })
+            })
+            .collect()
+    };
+
+    Ok(PortfolioResponse {
+        listings: get_listings_seller(deps, address.clone(), None, limit)?,
+        offers_made: get_offers_address(deps, address.clone(), None, limit)?,
+        offers_received: get_offers_seller(deps, address.clone(), None, limit)?,
+        trades_proposed: get_trades_address(deps, address.clone(), None, limit)?,
+        trades_received: get_trades_seller(deps, address.clone(), None, limit)?,
+        escrowed: totals(&OFFERED_FUNDS)?,
+        withdrawable: totals(&BALANCES)?,
+    })
+}
+
+pub fn get_collection_stats(deps: Deps) -> StdResult<CollectionStatsResponse> {
+    let stats = STATS.load(deps.storage)?;
+
+    let currencies = [CoinType::Native, CoinType::Cw20]
+        .into_iter()
+        .map(|currency| {
+            // the cheapest listing priced in the currency or taking either
+            let floor_price = listing_coins(Some(&currency))
+                .into_iter()
+                .map(|coin| {
+                    listings()
+                        .idx
+                        .buy_now_price
+                        .sub_prefix(coin)
+                        .range(deps.storage, None, None, Order::Ascending)
+                        .next()
+                        .transpose()
+                })
+                .collect::<StdResult<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .map(|(_, listing)| listing.price)
+                .min();
+            // offers that are left after their listing closed wait for refunds
+            let highest_offer = offers()
+                .idx
+                .listed_amount
+                .sub_prefix(coin_key(&currency))
+                .range(deps.storage, None, None, Order::Descending)
+                .map(|item| item.map(|(_, offer)| offer))
+                .find(|item| match item {
+                    Ok(offer) => !CLOSED_LISTINGS.has(deps.storage, offer.asked_id.clone()),
+                    Err(_) => true,
+                })
+                .transpose()?;
+            Ok(CurrencyStats {
+                floor_price,
+                highest_offer,
+                volume: VOLUME
+                    .may_load(deps.storage, coin_key(&currency))?
+                    .unwrap_or_default(),
+                currency,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(CollectionStatsResponse {
+        active_listings: LISTING_COUNTER.load(deps.storage)?,
+        open_offers: stats.open_offers,
+        sale_count: stats.sale_count,
+        currencies,
+    })
+}
+
+pub fn get_balance(deps: Deps, address: String, asset: CoinType) -> StdResult<Uint256> {
+    let config = CONFIG.load(deps.storage)?;
+    let balance = BALANCES
+        .may_load(deps.storage, (address, asset_key(&config, &asset)))?
+        .unwrap_or_default();
+    Ok(balance)
+}
+
+pub fn get_solvency(deps: Deps, env: Env) -> StdResult<Vec<SolvencyResponse>> {
+    let config = CONFIG.load(deps.storage)?;
+
+    [CoinType::Native, CoinType::Cw20]
+        .into_iter()
+        .map(|asset| {
+            let key = asset_key(&config, &asset);
+            let escrowed = ESCROWED
+                .may_load(deps.storage, key.clone())?
+                .unwrap_or_default();
+            let credited = CREDITED.may_load(deps.storage, key)?.unwrap_or_default();
+
+            let balance = match asset {
+                CoinType::Native => {
+                    deps.querier
+                        .query_balance(&env.contract.address, NATIVE_DENOM)?
+                        .amount
+                }
+                CoinType::Cw20 => Cw20Contract(config.cw20_address.clone())
+                    .balance(&deps.querier, &env.contract.address)?,
+            };
+            let balance = Uint256::from_uint128(balance);
+
+            Ok(SolvencyResponse {
+                asset,
+                escrowed,
+                credited,
+                balance,
+                covered: balance >= escrowed + credited,
+            })
+        })
+        .collect()
 }
 
 pub fn get_listing(deps: Deps, id: String) -> StdResult<Listing> {
-    let listing = LISTINGS.load(deps.storage, id)?;
+    let listing = listings().load(deps.storage, id)?;
     Ok(listing)
 }
 
 pub fn get_trade(deps: Deps, id: String, trader: String) -> StdResult<Trade> {
-    let trade = TRADES.load(deps.storage, (id, trader))?;
+    let trade = trades().load(deps.storage, (id, trader))?;
     Ok(trade)
 }
 
 pub fn get_offer(deps: Deps, id: String, offerer: String) -> StdResult<Offer> {
-    let offer = OFFERS.load(deps.storage, (id, offerer))?;
+    let offer = offers().load(deps.storage, (id, offerer))?;
     Ok(offer)
 }
 
+pub fn get_mixed_offer(deps: Deps, id: String, offerer: String) -> StdResult<MixedOffer> {
+    let mixed = MIXED_OFFERS.load(deps.storage, (id, offerer))?;
+    Ok(mixed)
+}
+
+pub fn get_swap(deps: Deps, id: String, trader: String) -> StdResult<Swap> {
+    load_swap(deps.storage, id, trader)
+}
+
+pub fn get_ring(deps: Deps, id: u64) -> StdResult<Ring> {
+    let ring = RINGS.load(deps.storage, id)?;
+    Ok(ring)
+}
+
+pub fn get_direct_trade(
+    deps: Deps,
+    collection: String,
+    id: String,
+    trader: String,
+) -> StdResult<DirectTrade> {
+    let direct = DIRECT_TRADES.load(deps.storage, (collection, id, trader))?;
+    Ok(direct)
+}
+
+/// Collects up to `limit` entries from `entries`, reading one more to tell
+/// whether another page follows.
+fn paginate<T, C: Clone>(
+    entries: impl Iterator<Item = StdResult<(C, T)>>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<T, C>> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let mut entries: Vec<(C, T)> = entries.take(limit + 1).collect::<StdResult<_>>()?;
+
+    let next_start_after = if entries.len() > limit {
+        entries.truncate(limit);
+        entries.last().map(|(cursor, _)| cursor.clone())
+    } else {
+        None
+    };
+
+    Ok(PageResponse {
+        items: entries.into_iter().map(|(_, item)| item).collect(),
+        next_start_after,
+    })
+}
+
 pub fn get_listings_seller(
     deps: Deps,
     seller: String,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Listing>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
-
-    let listings: StdResult<Vec<Listing>> = LISTINGS
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .filter(|item| item.as_ref().unwrap().1.owner == seller)
-        .map(|item| item.map(|(_, listing)| listing))
-        .collect();
-    listings
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Listing, String>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        listings()
+            .idx
+            .owner
+            .prefix(seller)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
 }
 
 pub fn get_all_listings(
     deps: Deps,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Listing>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Listing, String>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        listings().range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
 
-    let listings: StdResult<Vec<Listing>> = LISTINGS
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .map(|item| item.map(|(_, listing)| listing))
+/// Joins a listing with the NFT's `nft_info` and `owner_of` from the
+/// collection. Either is left empty rather than failing the query when the
+/// collection cannot answer it.
+fn enrich_listing(deps: Deps, config: &Config, listing: Listing) -> EnrichedListing {
+    let nft_info = query_nft_metadata(deps, &config.cw721_address, &listing.nft_id).ok();
+    let nft_owner: Option<OwnerOfResponse> = deps
+        .querier
+        .query_wasm_smart(
+            config.cw721_address.to_string(),
+            &Cw721QueryMsg::OwnerOf {
+                token_id: listing.nft_id.clone(),
+                include_expired: Some(false),
+            },
+        )
+        .ok();
+
+    let (token_uri, extension) = nft_info.unwrap_or_default();
+    EnrichedListing {
+        listing,
+        token_uri,
+        extension,
+        current_owner: nft_owner.map(|owner| owner.owner),
+    }
+}
+
+pub fn get_enriched_listing(deps: Deps, id: String) -> StdResult<EnrichedListing> {
+    let config = CONFIG.load(deps.storage)?;
+    let listing = listings().load(deps.storage, id)?;
+    Ok(enrich_listing(deps, &config, listing))
+}
+
+pub fn get_enriched_listings(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<EnrichedListing, String>> {
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .min(MAX_ENRICHED_QUERY_LIMIT);
+    let page = get_all_listings(deps, start_after, Some(limit))?;
+
+    Ok(PageResponse {
+        items: page
+            .items
+            .into_iter()
+            .map(|listing| enrich_listing(deps, &config, listing))
+            .collect(),
+        next_start_after: page.next_start_after,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn get_listings_by_price(
+    deps: Deps,
+    min_price: Option<Uint256>,
+    max_price: Option<Uint256>,
+    currency: Option<CoinType>,
+    seller: Option<String>,
+    descending: Option<bool>,
+    start_after: Option<(Uint256, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Listing, (Uint256, String)>> {
+    // an empty token id sorts before every listing at the same price
+    let lower = min_price.map(|min| Bound::inclusive((price_key(min), String::new())));
+    let upper = match max_price {
+        Some(max) if max < Uint256::MAX => Some(Bound::exclusive((
+            price_key(max + Uint256::one()),
+            String::new(),
+        ))),
+        _ => None,
+    };
+    let cursor = start_after.map(|(price, id)| Bound::exclusive((price_key(price), id)));
+    let (order, min, max) = if descending.unwrap_or(false) {
+        (Order::Descending, lower, cursor.or(upper))
+    } else {
+        (Order::Ascending, cursor.or(lower), upper)
+    };
+
+    let ranges = listing_coins(currency.as_ref())
+        .into_iter()
+        .map(|coin| {
+            let range = match &seller {
+                Some(seller) => listings()
+                    .idx
+                    .owner_price
+                    .sub_prefix((seller.clone(), coin))
+                    .range(deps.storage, min.clone(), max.clone(), order),
+                None => listings().idx.price.sub_prefix(coin).range(
+                    deps.storage,
+                    min.clone(),
+                    max.clone(),
+                    order,
+                ),
+            };
+            Box::new(range.map(|item| item.map(|(id, listing)| ((listing.price, id), listing))))
+                as ListingRange
+        })
         .collect();
-    listings
+
+    paginate(merge_ranges(ranges, order), limit)
+}
+
+// a sorted range of index entries with their cursors
+type Range<'a, C, T> = Box<dyn Iterator<Item = StdResult<(C, T)>> + 'a>;
+type ListingRange<'a> = Range<'a, (Uint256, String), Listing>;
+
+// price index partitions holding the listings that take `currency`: those
+// priced in it and those taking either currency
+fn listing_coins(currency: Option<&CoinType>) -> Vec<String> {
+    match currency {
+        Some(currency) => vec![coin_key(currency), ANY_COIN.to_string()],
+        None => vec![
+            coin_key(&CoinType::Native),
+            coin_key(&CoinType::Cw20),
+            ANY_COIN.to_string(),
+        ],
+    }
+}
+
+// merges ranges that are each sorted in `order` into one sorted range;
+// errors come out right away
+fn merge_ranges<'a, C: Ord + Clone + 'a, T: 'a>(
+    ranges: Vec<Range<'a, C, T>>,
+    order: Order,
+) -> impl Iterator<Item = StdResult<(C, T)>> + 'a {
+    let mut ranges: Vec<_> = ranges.into_iter().map(Iterator::peekable).collect();
+    std::iter::from_fn(move || {
+        let mut next: Option<(usize, Option<C>)> = None;
+        for (i, range) in ranges.iter_mut().enumerate() {
+            let cursor = match range.peek() {
+                None => continue,
+                Some(Err(_)) => {
+                    next = Some((i, None));
+                    break;
+                }
+                Some(Ok((cursor, _))) => cursor,
+            };
+            let first = match &next {
+                Some((_, Some(best))) => match order {
+                    Order::Ascending => cursor < best,
+                    Order::Descending => cursor > best,
+                },
+                _ => true,
+            };
+            if first {
+                next = Some((i, Some(cursor.clone())));
+            }
+        }
+        next.and_then(|(i, _)| ranges[i].next())
+    })
 }
 
 pub fn get_trades_address(
     deps: Deps,
     address: String,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Trade>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Trade, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        trades()
+            .idx
+            .trader
+            .prefix(address)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
 
-    let trades: StdResult<Vec<Trade>> = TRADES
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .filter(|item| item.as_ref().unwrap().1.trader == address)
-        .map(|item| item.map(|(_, trade)| trade))
-        .collect();
-    trades
+pub fn get_trades_seller(
+    deps: Deps,
+    seller: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Trade, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        trades()
+            .idx
+            .seller
+            .prefix(seller)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
 }
 
 pub fn get_trades_id(
     deps: Deps,
     id: String,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Trade>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
-
-    let trades: StdResult<Vec<Trade>> = TRADES
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .filter(|item| item.as_ref().unwrap().1.asked_id == id)
-        .map(|item| item.map(|(_, trade)| trade))
-        .collect();
-    trades
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Trade, String>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        trades()
+            .prefix(id)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
 }
 
 pub fn get_all_trades(
     deps: Deps,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Trade>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
-
-    let trades: StdResult<Vec<Trade>> = TRADES
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .map(|item| item.map(|(_, trade)| trade))
-        .collect();
-    trades
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Trade, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        trades().range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
 }
 
 pub fn get_offers_address(
     deps: Deps,
     address: String,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Offer>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Offer, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        offers()
+            .idx
+            .offerer
+            .prefix(address)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
 
-    let offers: StdResult<Vec<Offer>> = OFFERS
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .filter(|item| item.as_ref().unwrap().1.offerer == address)
-        .map(|item| item.map(|(_, offer)| offer))
-        .collect();
-    offers
+pub fn get_offers_seller(
+    deps: Deps,
+    seller: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Offer, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        offers()
+            .idx
+            .seller
+            .prefix(seller)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
 }
 
 pub fn get_offers_id(
     deps: Deps,
     id: String,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Offer>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
-
-    let offers: StdResult<Vec<Offer>> = OFFERS
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .filter(|item| item.as_ref().unwrap().1.asked_id == id)
-        .map(|item| item.map(|(_, offer)| offer))
-        .collect();
-    offers
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Offer, String>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        offers()
+            .prefix(id)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
 }
 
 pub fn get_all_offers(
     deps: Deps,
-    from_index: Option<u64>,
-    limit: Option<u64>,
-) -> StdResult<Vec<Offer>> {
-    let from_index = from_index.unwrap_or(0);
-    let limit = limit.unwrap_or(10);
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Offer, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        offers().range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
 
-    let offers: StdResult<Vec<Offer>> = OFFERS
-        .range(deps.storage, None, None, Order::Ascending)
-        .skip(from_index as usize)
-        .take(limit as usize)
-        .map(|item| item.map(|(_, offer)| offer))
-        .collect();
-    offers
+pub fn get_highest_offer(deps: Deps, id: String) -> StdResult<HighestOfferResponse> {
+    let highest = |currency: CoinType| -> StdResult<Option<Offer>> {
+        let best = offers()
+            .idx
+            .token_amount
+            .sub_prefix((id.clone(), coin_key(&currency)))
+            .range(deps.storage, None, None, Order::Descending)
+            .next()
+            .transpose()?;
+        Ok(best.map(|(_, offer)| offer))
+    };
+
+    Ok(HighestOfferResponse {
+        native: highest(CoinType::Native)?,
+        cw20: highest(CoinType::Cw20)?,
+    })
+}
+
+pub fn get_offers_by_price(
+    deps: Deps,
+    id: String,
+    currency: CoinType,
+    start_after: Option<(Uint256, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Offer, (Uint256, String)>> {
+    let end = start_after
+        .map(|(amount, offerer)| Bound::exclusive((price_key(amount), (id.clone(), offerer))));
+    paginate(
+        offers()
+            .idx
+            .token_amount
+            .sub_prefix((id, coin_key(&currency)))
+            .range(deps.storage, None, end, Order::Descending)
+            .map(|item| item.map(|((_, offerer), offer)| ((offer.amount_offered, offerer), offer))),
+        limit,
+    )
+}
+
+pub fn get_mixed_offers_id(
+    deps: Deps,
+    id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<MixedOffer, String>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        MIXED_OFFERS
+            .prefix(id)
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
+
+pub fn get_direct_trades_token(
+    deps: Deps,
+    collection: String,
+    id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<DirectTrade, String>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        DIRECT_TRADES
+            .prefix((collection, id))
+            .range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
+
+pub fn get_custody(deps: Deps, collection: String, id: String) -> StdResult<Custody> {
+    let custody = CUSTODY.load(deps.storage, (collection, id))?;
+    Ok(custody)
+}
+
+pub fn get_all_custody(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Custody, (String, String)>> {
+    let start = start_after.map(Bound::exclusive);
+    paginate(
+        CUSTODY.range(deps.storage, start, None, Order::Ascending),
+        limit,
+    )
+}
+
+pub fn get_sales_token(
+    deps: Deps,
+    id: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Sale, u64>> {
+    let end = start_after.map(Bound::exclusive);
+    paginate(
+        sales()
+            .idx
+            .token
+            .prefix(id)
+            .range(deps.storage, None, end, Order::Descending),
+        limit,
+    )
+}
+
+// swaps are in the history with a price of 0, which is not a price
+pub fn get_last_sale(deps: Deps, id: String) -> StdResult<Option<Sale>> {
+    Ok(get_price_history(deps, id, Some(1))?.pop())
+}
+
+pub fn get_price_history(deps: Deps, id: String, limit: Option<u32>) -> StdResult<Vec<Sale>> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    sales()
+        .idx
+        .priced
+        .prefix(id)
+        .range(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|(_, sale)| sale))
+        .collect()
+}
+
+pub fn get_sales_address(
+    deps: Deps,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Sale, u64>> {
+    let mut sold = sales()
+        .idx
+        .seller
+        .prefix(address.clone())
+        .range(
+            deps.storage,
+            None,
+            start_after.map(Bound::exclusive),
+            Order::Descending,
+        )
+        .peekable();
+    let mut bought = sales()
+        .idx
+        .buyer
+        .prefix(address)
+        .range(
+            deps.storage,
+            None,
+            start_after.map(Bound::exclusive),
+            Order::Descending,
+        )
+        .peekable();
+
+    // merge both newest first; errors come out right away
+    let merged = std::iter::from_fn(move || {
+        let next_sold = sold
+            .peek()
+            .map(|item| item.as_ref().map_or(u64::MAX, |(id, _)| *id));
+        let next_bought = bought
+            .peek()
+            .map(|item| item.as_ref().map_or(u64::MAX, |(id, _)| *id));
+        match (next_sold, next_bought) {
+            // the seller bought their own listing
+            (Some(sold_id), Some(bought_id)) if sold_id == bought_id => {
+                bought.next();
+                sold.next()
+            }
+            (Some(sold_id), Some(bought_id)) if sold_id > bought_id => sold.next(),
+            (Some(_), None) => sold.next(),
+            _ => bought.next(),
+        }
+    });
+
+    paginate(merged, limit)
+}
+
+pub fn get_all_sales(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PageResponse<Sale, u64>> {
+    let end = start_after.map(Bound::exclusive);
+    paginate(
+        sales().range(deps.storage, None, end, Order::Descending),
+        limit,
+    )
+}
+
+fn failure(reason: FailureReason) -> StdResult<SimulationResponse> {
+    Ok(SimulationResponse::Failure { reason })
+}
+
+/// Turns the outcome of the checks an execute handler runs into a simulation.
+fn simulation(checked: Result<Settlement, ContractError>) -> StdResult<SimulationResponse> {
+    let reason = match checked {
+        Ok(settlement) => return Ok(SimulationResponse::Success { settlement }),
+        Err(ContractError::Unauthorized {}) => FailureReason::Unauthorized {},
+        Err(ContractError::BuyNowDisabled {}) => FailureReason::BuyNowDisabled {},
+        Err(ContractError::CurrencyNotAccepted {}) => FailureReason::CurrencyNotAccepted {},
+        Err(ContractError::IncorrectPayment { price }) => FailureReason::IncorrectPayment { price },
+        Err(ContractError::TradeExpired {}) => FailureReason::TradeExpired {},
+        Err(ContractError::DepositsIncomplete {}) => FailureReason::DepositsIncomplete {},
+        Err(err) => FailureReason::Other {
+            message: err.to_string(),
+        },
+    };
+    failure(reason)
+}
+
+fn nft_transfer(collection: &Addr, token_id: &str, recipient: &Addr) -> NftTransfer {
+    NftTransfer {
+        collection: collection.to_string(),
+        token_id: token_id.to_string(),
+        recipient: recipient.to_string(),
+    }
+}
+
+pub fn simulate_buy(
+    deps: Deps,
+    id: String,
+    buyer: String,
+    funds: AssetAmount,
+) -> StdResult<SimulationResponse> {
+    let listing = match listings().may_load(deps.storage, id)? {
+        Some(listing) => listing,
+        None => return failure(FailureReason::NotListed {}),
+    };
+    let buyer = match deps.api.addr_validate(&buyer) {
+        Ok(buyer) => buyer,
+        Err(err) => return simulation(Err(err.into())),
+    };
+    let config = CONFIG.load(deps.storage)?;
+
+    simulation(
+        check_payment(&listing, &funds.asset, funds.amount).map(|_| Settlement {
+            proceeds: Some(funds),
+            paid_to_wallet: pays_to_wallet(deps, &config, &listing.owner),
+            nft_transfers: vec![nft_transfer(&config.cw721_address, &listing.nft_id, &buyer)],
+        }),
+    )
+}
+
+pub fn simulate_accept_offer(
+    deps: Deps,
+    id: String,
+    offerer: String,
+    seller: String,
+) -> StdResult<SimulationResponse> {
+    let offer = match offers().may_load(deps.storage, (id.clone(), offerer))? {
+        Some(offer) => offer,
+        None => return failure(FailureReason::OfferNotFound {}),
+    };
+    let listing = match listings().may_load(deps.storage, id)? {
+        Some(listing) => listing,
+        None => return failure(FailureReason::NotListed {}),
+    };
+    let seller = match deps.api.addr_validate(&seller) {
+        Ok(seller) => seller,
+        Err(err) => return simulation(Err(err.into())),
+    };
+    let config = CONFIG.load(deps.storage)?;
+
+    simulation(check_owner(&listing, &seller).map(|_| Settlement {
+        proceeds: Some(AssetAmount {
+            asset: offer.amount_type.clone(),
+            amount: offer.amount_offered,
+        }),
+        paid_to_wallet: pays_to_wallet(deps, &config, &listing.owner),
+        nft_transfers: vec![nft_transfer(
+            &config.cw721_address,
+            &offer.asked_id,
+            &offer.offerer,
+        )],
+    }))
+}
+
+pub fn simulate_accept_trade(
+    deps: Deps,
+    env: Env,
+    id: String,
+    trader: String,
+    seller: String,
+) -> StdResult<SimulationResponse> {
+    let seller = match deps.api.addr_validate(&seller) {
+        Ok(seller) => seller,
+        Err(err) => return simulation(Err(err.into())),
+    };
+    let config = CONFIG.load(deps.storage)?;
+
+    let trade = match trades().may_load(deps.storage, (id.clone(), trader.clone()))? {
+        Some(trade) => trade,
+        None => {
+            let swap = match SWAP_TARGETS.may_load(deps.storage, (id, trader.clone()))? {
+                Some(first_id) => SWAPS.load(deps.storage, (first_id, trader))?,
+                None => return failure(FailureReason::TradeNotFound {}),
+            };
+            return simulation(check_swap(deps, &swap, &seller).map(|_| {
+                let asked = swap
+                    .asked_ids
+                    .iter()
+                    .map(|asked_id| nft_transfer(&config.cw721_address, asked_id, &swap.trader));
+                let offered = swap
+                    .offered
+                    .iter()
+                    .map(|nft| nft_transfer(&nft.collection, &nft.token_id, &seller));
+                Settlement {
+                    proceeds: None,
+                    paid_to_wallet: false,
+                    nft_transfers: asked.chain(offered).collect(),
+                }
+            }));
+        }
+    };
+    let listing = match listings().may_load(deps.storage, id)? {
+        Some(listing) => listing,
+        None => return failure(FailureReason::NotListed {}),
+    };
+
+    simulation(
+        check_trade(&listing, &trade, &seller, &env.block).map(|_| Settlement {
+            proceeds: None,
+            paid_to_wallet: false,
+            nft_transfers: vec![
+                nft_transfer(&config.cw721_address, &trade.asked_id, &trade.trader),
+                nft_transfer(
+                    trade_collection(&config, &trade),
+                    &trade.to_trade_id,
+                    &listing.owner,
+                ),
+            ],
+        }),
+    )
 }
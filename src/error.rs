@@ -2,6 +2,7 @@ use cosmwasm_std::{
     CheckedFromRatioError, CheckedMultiplyFractionError, ConversionOverflowError,
     Decimal256RangeExceeded, DivideByZeroError, OverflowError, StdError, Uint256,
 };
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -21,12 +22,87 @@ pub enum ContractError {
     #[error("The NFT is not tradable.")]
     NonTradeable {},
 
+    #[error("The listing cannot be bought at its price")]
+    BuyNowDisabled {},
+
+    #[error("The listing does not take this currency")]
+    CurrencyNotAccepted {},
+
+    #[error("The listing does not take offers")]
+    OffersDisabled {},
+
+    #[error("Offers on the listing must be at least {min_offer}")]
+    OfferTooLow { min_offer: Uint256 },
+
+    #[error("Offer thresholds need a listing currency")]
+    RulesNeedCurrency {},
+
     #[error("User is not the NFT owner")]
     NotNftOwner {},
 
     #[error("Type Sent is Not Supported")]
     TypeNotSupported {},
 
+    #[error("Refunds for a previous listing of this NFT are still pending")]
+    RefundsPending {},
+
+    #[error("The listing has no pending refunds")]
+    NotClosed {},
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("An offer from this address already exists for the NFT")]
+    OfferExists {},
+
+    #[error("A trade from this address already exists for the NFT")]
+    TradeExists {},
+
+    #[error("Offers and swaps must include between 1 and {max} NFTs")]
+    InvalidNftCount { max: u32 },
+
+    #[error("The same NFT is included more than once")]
+    DuplicateNft {},
+
+    #[error("The collection is not accepted by the marketplace")]
+    CollectionNotAllowed {},
+
+    #[error("The NFT is not part of the offer or is already deposited")]
+    UnexpectedNft {},
+
+    #[error("Not every offered NFT has been deposited yet")]
+    DepositsIncomplete {},
+
+    #[error("All asked NFTs must be listed by the same owner")]
+    MixedOwners {},
+
+    #[error("The swap cannot be refunded before its deposit deadline")]
+    SwapNotRefundable {},
+
+    #[error("A ring trade needs between {min} and {max} distinct participants")]
+    InvalidRing { min: u32, max: u32 },
+
+    #[error("The ring is fully deposited or its deposit deadline has not passed")]
+    RingNotRefundable {},
+
+    #[error("The offered NFT is not what the seller accepts in trade")]
+    TradeNotWanted {},
+
+    #[error("The trade has expired")]
+    TradeExpired {},
+
+    #[error("The NFT is listed, trade it through its listing")]
+    NftListed {},
+
+    #[error("The NFT is already held by the marketplace")]
+    AlreadyInCustody {},
+
+    #[error("Cannot migrate from {contract} {version}")]
+    CannotMigrate { contract: String, version: String },
+
+    #[error("Insufficient balance: {balance}")]
+    InsufficientBalance { balance: Uint256 },
+
     #[error("{0}")]
     DivideByZeroError(#[from] DivideByZeroError),
 
@@ -44,4 +120,7 @@ pub enum ContractError {
 
     #[error("{0}")]
     CheckedMultiplyFractionError(#[from] CheckedMultiplyFractionError),
+
+    #[error("{0}")]
+    SemVer(#[from] semver::Error),
 }
@@ -1,13 +1,47 @@
-use crate::state::{Listing, Offer, Trade};
+use crate::state::{
+    AcceptancePolicy, CoinType, Custody, DirectTrade, Listing, MixedOffer, Offer, Ring, Sale, Swap,
+    Trade, TraitFilter,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::Uint256;
 use cw20::Cw20ReceiveMsg;
 use cw721::Cw721ReceiveMsg;
+use cw_utils::Expiration;
+use schemars::JsonSchema;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub cw721_address: String,
     pub cw20_address: String,
+    pub push_to_wallets: Option<bool>,
+    // other cw721 contracts whose NFTs can be offered, besides cw721_address
+    pub collections: Option<Vec<String>>,
+}
+
+#[cw_serde]
+pub struct NftRef {
+    pub collection: String,
+    pub token_id: String,
+}
+
+#[cw_serde]
+pub struct TradePreferencesMsg {
+    #[serde(default)]
+    pub token_ids: Vec<NftRef>,
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub traits: Vec<TraitFilter>,
+    #[serde(default)]
+    pub auto_accept: bool,
+}
+
+#[cw_serde]
+pub struct RingParticipant {
+    pub address: String,
+    pub nft: NftRef,
 }
 
 #[cw_serde]
@@ -30,6 +64,71 @@ pub enum ExecuteMsg {
         id: String,
         offerer: String,
     },
+    /// Offers NFTs plus any native funds attached for a listing. Each NFT is
+    /// then escrowed with `send_nft` and `DepositMixedOffer`.
+    MixedOffer {
+        target: String, // target id
+        nfts: Vec<NftRef>,
+    },
+    AcceptMixedOffer {
+        id: String,
+        offerer: String,
+    },
+    CancelMixedOffer {
+        id: String,
+    },
+    /// Returns the NFTs of expired trades, scanning at most `limit` trades
+    /// after `start_after`. Anyone can call it.
+    ExpireTrades {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    /// Accepts a direct trade for an NFT the sender approved the marketplace
+    /// for. It can also be accepted by sending the NFT with `AcceptDirectTrade`.
+    AcceptDirectTrade {
+        collection: String,
+        id: String,
+        trader: String,
+    },
+    RejectDirectTrade {
+        collection: String,
+        id: String,
+        trader: String,
+    },
+    CancelDirectTrade {
+        collection: String,
+        id: String,
+    },
+    /// Proposes several NFTs for several listings of the same owner. Offered
+    /// NFTs are escrowed with `send_nft` and `DepositSwap`, or approved to the
+    /// marketplace, and the listing owner settles it with `AcceptTrade`.
+    ProposeSwap {
+        asked_ids: Vec<String>,
+        offered: Vec<NftRef>,
+    },
+    /// Removes a swap after its deposit deadline, returning the deposited
+    /// NFTs. Anyone can call it while the swap is partly deposited, otherwise
+    /// only the trader.
+    RefundSwap {
+        id: String,
+        trader: String,
+    },
+    /// Proposes a ring trade where each participant gives their NFT to the
+    /// next one and the last participant gives theirs to the first. Every
+    /// participant commits by sending their NFT with `DepositRing`.
+    ProposeRing {
+        participants: Vec<RingParticipant>,
+    },
+    /// Pulls a participant out of a ring that has not settled, returning
+    /// every deposited NFT.
+    WithdrawRing {
+        id: u64,
+    },
+    /// Returns the deposited NFTs of a ring that was not fully deposited
+    /// before its deadline. Anyone can call it.
+    RefundRing {
+        id: u64,
+    },
     AcceptTrade {
         id: String,
         trader: String,
@@ -37,9 +136,42 @@ pub enum ExecuteMsg {
     CancelTrade {
         id: String,
     },
+    RejectTrade {
+        id: String,
+        trader: String,
+    },
+    /// Rejects the trades on a listing in batches of up to 30.
+    RejectAllTrades {
+        id: String,
+    },
     CancelListing {
         id: String,
     },
+    /// Sets the offer floor of the listing's policy and the amount at which
+    /// offers are accepted right away. `None` removes either rule.
+    SetOfferRules {
+        id: String,
+        min_offer: Option<Uint256>,
+        auto_accept_at: Option<Uint256>,
+    },
+    /// Replaces what the listing accepts in trade; `None` accepts any NFT.
+    SetTradePreferences {
+        id: String,
+        preferences: Option<TradePreferencesMsg>,
+    },
+    /// Settles offers and trades left over when a listing closed with more
+    /// than one batch outstanding, sending at most `limit` refunds and NFTs.
+    /// Anyone can call it.
+    ProcessClosedListing {
+        id: String,
+        limit: Option<u32>,
+    },
+    /// Withdraws proceeds and refunds credited to the sender. Takes the whole
+    /// balance when no amount is given.
+    Withdraw {
+        asset: CoinType,
+        amount: Option<Uint256>,
+    },
     Receive(Cw20ReceiveMsg),
     ReceiveNft(Cw721ReceiveMsg),
 }
@@ -53,12 +185,44 @@ pub enum ReceiveMsg {
         target: String, // target id
         offered_price: Uint256,
     },
+    MixedOffer {
+        target: String, // target id
+        nfts: Vec<NftRef>,
+    },
 }
 
 #[cw_serde]
 pub enum ReceiveNftMsg {
-    NewListing { price: Uint256, tradeable: bool },
-    NewTrade { target: String },
+    NewListing {
+        price: Uint256,
+        policy: AcceptancePolicy,
+        preferences: Option<TradePreferencesMsg>,
+        auto_accept_at: Option<Uint256>,
+        currency: Option<CoinType>,
+    },
+    NewTrade {
+        target: String,
+        expires: Option<Expiration>,
+    },
+    DepositMixedOffer {
+        target: String,
+    },
+    DepositSwap {
+        target: String,
+    },
+    /// Offers the sent NFT for a token that is not listed. `collection`
+    /// defaults to the marketplace collection.
+    ProposeDirectTrade {
+        collection: Option<String>,
+        target: String,
+        expires: Option<Expiration>,
+    },
+    AcceptDirectTrade {
+        trader: String,
+    },
+    DepositRing {
+        id: u64,
+    },
 }
 
 #[cw_serde]
@@ -66,58 +230,386 @@ pub enum ReceiveNftMsg {
 pub enum QueryMsg {
     #[returns(Listing)]
     GetListing { id: String },
-    #[returns(Vec<Listing>)]
+    #[returns(PageResponse<Listing, String>)]
     GetListingsBySeller {
         seller: String,
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<String>,
+        limit: Option<u32>,
     },
-    #[returns(Vec<Listing>)]
+    #[returns(PageResponse<Listing, String>)]
     GetAllListings {
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Listings sorted by price, cheapest first unless `descending`, within
+    /// an optional price range and optionally only those of one seller or
+    /// that take `currency`.
+    #[returns(PageResponse<Listing, (Uint256, String)>)]
+    GetListingsByPrice {
+        min_price: Option<Uint256>,
+        max_price: Option<Uint256>,
+        currency: Option<CoinType>,
+        seller: Option<String>,
+        descending: Option<bool>,
+        start_after: Option<(Uint256, String)>,
+        limit: Option<u32>,
+    },
+    /// A listing with the NFT's metadata and its holder in the collection.
+    #[returns(EnrichedListing)]
+    GetEnrichedListing { id: String },
+    /// `GetAllListings` with the metadata and holder of every NFT, at most 30
+    /// per page.
+    #[returns(PageResponse<EnrichedListing, String>)]
+    GetEnrichedListings {
+        start_after: Option<String>,
+        limit: Option<u32>,
     },
     #[returns(u128)]
     GetListingCount {},
     #[returns(Trade)]
     GetTrade { id: String, trader: String },
-    #[returns(Vec<Trade>)]
+    #[returns(PageResponse<Trade, (String, String)>)]
     GetTradesByAddress {
         address: String,
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
     },
-    #[returns(Vec<Trade>)]
+    #[returns(PageResponse<Trade, String>)]
     GetTradesById {
         id: String,
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<String>,
+        limit: Option<u32>,
     },
-    #[returns(Vec<Trade>)]
+    #[returns(PageResponse<Trade, (String, String)>)]
     GetAllTrades {
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    /// Trades on the listings of a seller.
+    #[returns(PageResponse<Trade, (String, String)>)]
+    GetTradesBySeller {
+        seller: String,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
     },
     #[returns(Offer)]
     GetOffer { id: String, offerer: String },
-    #[returns(Vec<Offer>)]
+    #[returns(PageResponse<Offer, (String, String)>)]
     GetOffersByAddress {
         address: String,
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
     },
-    #[returns(Vec<Offer>)]
+    #[returns(PageResponse<Offer, String>)]
     GetOffersById {
         id: String,
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<String>,
+        limit: Option<u32>,
     },
-    #[returns(Vec<Offer>)]
+    #[returns(PageResponse<Offer, (String, String)>)]
     GetAllOffers {
-        from_index: Option<u64>,
-        limit: Option<u64>,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    /// Offers on the listings of a seller.
+    #[returns(PageResponse<Offer, (String, String)>)]
+    GetOffersBySeller {
+        seller: String,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
     },
+    /// The best offer on a token in each currency.
+    #[returns(HighestOfferResponse)]
+    HighestOffer { id: String },
+    /// Offers on a token in one currency, the highest first.
+    #[returns(PageResponse<Offer, (Uint256, String)>)]
+    OffersSortedByPrice {
+        id: String,
+        currency: CoinType,
+        start_after: Option<(Uint256, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(MixedOffer)]
+    GetMixedOffer { id: String, offerer: String },
+    #[returns(PageResponse<MixedOffer, String>)]
+    GetMixedOffersById {
+        id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Swap)]
+    GetSwap { id: String, trader: String },
+    #[returns(Ring)]
+    GetRing { id: u64 },
+    #[returns(DirectTrade)]
+    GetDirectTrade {
+        collection: String,
+        id: String,
+        trader: String,
+    },
+    #[returns(PageResponse<DirectTrade, String>)]
+    GetDirectTradesByToken {
+        collection: String,
+        id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Custody)]
+    GetCustody { collection: String, id: String },
+    #[returns(PageResponse<Custody, (String, String)>)]
+    GetAllCustody {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    /// Sales of a token, the most recent first.
+    #[returns(PageResponse<Sale, u64>)]
+    GetSalesByToken {
+        id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Sales where the address was the seller or the buyer, the most recent
+    /// first.
+    #[returns(PageResponse<Sale, u64>)]
+    GetSalesByAddress {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(PageResponse<Sale, u64>)]
+    GetAllSales {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The latest sale of a token with a price; swaps are left out.
+    #[returns(Option<Sale>)]
+    LastSale { id: String },
+    /// The latest sales of a token with a price, the most recent first.
+    #[returns(Vec<Sale>)]
+    PriceHistory { id: String, limit: Option<u32> },
+    /// Listings, offers and trades of an address, made and received, with
+    /// up to 100 entries in each list, and its escrowed and credited funds.
+    #[returns(PortfolioResponse)]
+    Portfolio { address: String },
+    /// Open listings and offers, sales so far, and per currency the floor
+    /// price, highest offer and volume.
+    #[returns(CollectionStatsResponse)]
+    CollectionStats {},
+    /// Runs the checks of `Buy` for `buyer` paying `funds`, the amount sent
+    /// with the message or through the cw20 contract.
+    #[returns(SimulationResponse)]
+    SimulateBuy {
+        id: String,
+        buyer: String,
+        funds: AssetAmount,
+    },
+    /// Runs the checks of `AcceptOffer` as if `seller` sent it.
+    #[returns(SimulationResponse)]
+    SimulateAcceptOffer {
+        id: String,
+        offerer: String,
+        seller: String,
+    },
+    /// Runs the checks of `AcceptTrade` as if `seller` sent it, for a trade
+    /// or a swap.
+    #[returns(SimulationResponse)]
+    SimulateAcceptTrade {
+        id: String,
+        trader: String,
+        seller: String,
+    },
+    #[returns(Uint256)]
+    Balance { address: String, asset: CoinType },
+    #[returns(Vec<SolvencyResponse>)]
+    Solvency {},
 }
 
+/// A page of a list query. Pass `next_start_after` as `start_after` to get
+/// the next page; it is `None` on the last one.
 #[cw_serde]
-pub struct MigrateMsg {}
+pub struct PageResponse<T, C> {
+    pub items: Vec<T>,
+    pub next_start_after: Option<C>,
+}
+
+// amounts in different currencies are not compared, so there is one per currency
+#[cw_serde]
+pub struct HighestOfferResponse {
+    pub native: Option<Offer>,
+    pub cw20: Option<Offer>,
+}
+
+// each list is the first page of the matching paginated query, whose
+// `next_start_after` continues it there
+#[cw_serde]
+pub struct PortfolioResponse {
+    // `GetListingsBySeller`
+    pub listings: PageResponse<Listing, String>,
+    // `GetOffersByAddress`
+    pub offers_made: PageResponse<Offer, (String, String)>,
+    // offers on the address's listings, `GetOffersBySeller`
+    pub offers_received: PageResponse<Offer, (String, String)>,
+    // `GetTradesByAddress`
+    pub trades_proposed: PageResponse<Trade, (String, String)>,
+    // trades on the address's listings, `GetTradesBySeller`
+    pub trades_received: PageResponse<Trade, (String, String)>,
+    // funds held for the address's offers and mixed offers
+    pub escrowed: Vec<AssetAmount>,
+    // credited to the address and not withdrawn yet
+    pub withdrawable: Vec<AssetAmount>,
+}
+
+#[cw_serde]
+pub struct AssetAmount {
+    pub asset: CoinType,
+    pub amount: Uint256,
+}
+
+#[cw_serde]
+pub enum SimulationResponse {
+    Success { settlement: Settlement },
+    Failure { reason: FailureReason },
+}
+
+// what settling would do; the marketplace takes no fees or royalties, so the
+// seller gets the whole price
+#[cw_serde]
+pub struct Settlement {
+    // paid to the seller, unset for trades
+    pub proceeds: Option<AssetAmount>,
+    // proceeds go straight to the seller's wallet instead of their balance
+    pub paid_to_wallet: bool,
+    pub nft_transfers: Vec<NftTransfer>,
+}
+
+#[cw_serde]
+pub struct NftTransfer {
+    pub collection: String,
+    pub token_id: String,
+    pub recipient: String,
+}
+
+#[cw_serde]
+pub enum FailureReason {
+    NotListed {},
+    OfferNotFound {},
+    TradeNotFound {},
+    Unauthorized {},
+    BuyNowDisabled {},
+    CurrencyNotAccepted {},
+    IncorrectPayment { price: Uint256 },
+    TradeExpired {},
+    DepositsIncomplete {},
+    Other { message: String },
+}
+
+#[cw_serde]
+pub struct CollectionStatsResponse {
+    pub active_listings: u128,
+    pub open_offers: u64,
+    // buys and accepted offers, in either currency
+    pub sale_count: u64,
+    pub currencies: Vec<CurrencyStats>,
+}
+
+// native and cw20 amounts are never added up or compared with each other
+#[cw_serde]
+pub struct CurrencyStats {
+    pub currency: CoinType,
+    // cheapest listing that can be bought at its price in this currency
+    pub floor_price: Option<Uint256>,
+    // best offer in this currency on a listed NFT
+    pub highest_offer: Option<Offer>,
+    // sum of the prices of the sales in this currency
+    pub volume: Uint256,
+}
+
+#[cw_serde]
+pub struct EnrichedListing {
+    pub listing: Listing,
+    // unset when the collection's `nft_info` cannot be read; `extension` is
+    // also unset when it isn't in the `NftMetadata` layout
+    pub token_uri: Option<String>,
+    pub extension: Option<NftMetadata>,
+    // holder according to the collection. Listed NFTs are held by the
+    // marketplace (the seller is `listing.owner`), so this only tells whether
+    // the NFT is still in custody
+    pub current_owner: Option<String>,
+}
+
+// on-chain metadata in the usual cw721 layout; fields the collection doesn't
+// set are left empty and unknown ones are dropped, so no deny_unknown_fields.
+// Trait filters read their `attributes` from it too
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftMetadata {
+    pub image: Option<String>,
+    pub image_data: Option<String>,
+    pub external_url: Option<String>,
+    pub description: Option<String>,
+    pub name: Option<String>,
+    pub attributes: Option<Vec<NftTrait>>,
+    pub background_color: Option<String>,
+    pub animation_url: Option<String>,
+    pub youtube_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftTrait {
+    pub display_type: Option<String>,
+    pub trait_type: String,
+    #[serde(deserialize_with = "trait_value")]
+    pub value: String,
+}
+
+// filters compare values as text, so numbers and booleans are read in their
+// JSON spelling instead of failing the whole extension
+fn trait_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    struct TraitValue;
+
+    impl<'de> Visitor<'de> for TraitValue {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string, integer or boolean")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<String, E> {
+            Ok(value.to_string())
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<String, E> {
+            Ok(value.to_string())
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<String, E> {
+            Ok(value.to_string())
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<String, E> {
+            Ok(value.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(TraitValue)
+}
+
+#[cw_serde]
+pub struct SolvencyResponse {
+    pub asset: CoinType,
+    // held for open offers
+    pub escrowed: Uint256,
+    // owed to users through their balances
+    pub credited: Uint256,
+    // what the contract actually holds
+    pub balance: Uint256,
+    pub covered: bool,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    // the first release paid sellers straight to their wallet, so unset keeps
+    // doing that
+    pub push_to_wallets: Option<bool>,
+    // other cw721 contracts whose NFTs can be offered, as on instantiate
+    pub collections: Option<Vec<String>>,
+}
@@ -1,11 +1,18 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint256};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Empty, StdResult, Storage, Timestamp, Uint256};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_utils::Expiration;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
 
 #[cw_serde]
 pub struct Config {
     pub cw721_address: Addr,
     pub cw20_address: Addr,
+    // pay plain wallets directly instead of crediting their balance
+    #[serde(default)]
+    pub push_to_wallets: bool,
 }
 
 #[cw_serde]
@@ -13,14 +20,72 @@ pub struct Listing {
     pub nft_id: String,
     pub price: Uint256,
     pub owner: Addr,
-    pub tradeable: bool,
+    pub policy: AcceptancePolicy,
+    #[serde(default)]
+    pub preferences: Option<TradePreferences>,
+    // offers of at least this much, in `currency`, are accepted as soon as
+    // they are made
+    #[serde(default)]
+    pub auto_accept_at: Option<Uint256>,
+    // the only currency taken for the listing, either one when unset
+    #[serde(default)]
+    pub currency: Option<CoinType>,
+}
+
+// how a listing can be bought
+#[cw_serde]
+pub enum AcceptancePolicy {
+    // any mix of buying at the price, offers of at least `min_offer` (in the
+    // listing's currency) and trades
+    Flexible {
+        buy_now: bool,
+        offers: bool,
+        min_offer: Option<Uint256>,
+        trades: bool,
+    },
+    // only offers, the seller accepts the best one; `price` is not used
+    AuctionOnly {
+        reserve: Option<Uint256>,
+    },
+}
+
+#[cw_serde]
+pub struct WantedNft {
+    pub collection: Addr,
+    pub token_id: String,
+}
+
+#[cw_serde]
+pub struct TraitFilter {
+    pub trait_type: String,
+    pub value: String,
+}
+
+// what a seller accepts in trade: any of `token_ids`, or an NFT passing the
+// collection and trait filters that are set
+#[cw_serde]
+pub struct TradePreferences {
+    pub token_ids: Vec<WantedNft>,
+    pub collection: Option<Addr>,
+    pub traits: Vec<TraitFilter>,
+    // settle right away when one of `token_ids` is offered
+    pub auto_accept: bool,
 }
 
 #[cw_serde]
 pub struct Trade {
     pub asked_id: String,
     pub to_trade_id: String,
+    // collection of the offered NFT, unset for trades made within the
+    // marketplace collection before other collections were accepted
+    #[serde(default)]
+    pub offered_collection: Option<Addr>,
     pub trader: Addr,
+    #[serde(default)]
+    pub expires: Expiration,
+    // owner of the asked listing
+    #[serde(default)]
+    pub seller: Option<Addr>,
 }
 
 #[cw_serde]
@@ -29,6 +94,115 @@ pub struct Offer {
     pub offerer: Addr,
     pub amount_offered: Uint256,
     pub amount_type: CoinType,
+    // owner of the asked listing, unset for offers on an NFT that is not listed
+    #[serde(default)]
+    pub seller: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct EscrowedNft {
+    pub collection: Addr,
+    pub token_id: String,
+    pub deposited: bool,
+}
+
+// NFTs from any whitelisted collection, plus an optional amount of currency
+#[cw_serde]
+pub struct MixedOffer {
+    pub asked_id: String,
+    pub offerer: Addr,
+    pub nfts: Vec<EscrowedNft>,
+    pub amount_offered: Uint256,
+    pub amount_type: CoinType,
+}
+
+// many-for-many proposal, stored under its first asked NFT
+#[cw_serde]
+pub struct Swap {
+    pub asked_ids: Vec<String>,
+    pub trader: Addr,
+    pub offered: Vec<EscrowedNft>,
+    pub deposit_deadline: Timestamp,
+}
+
+// swap proposal for an NFT that is not listed, addressed to its owner
+#[cw_serde]
+pub struct DirectTrade {
+    pub collection: Addr,
+    pub asked_id: String,
+    pub owner: Addr,
+    pub trader: Addr,
+    pub offered: EscrowedNft,
+    pub expires: Expiration,
+}
+
+// a participant of a ring trade and the NFT they give to the next participant
+#[cw_serde]
+pub struct RingLeg {
+    pub participant: Addr,
+    pub nft: EscrowedNft,
+}
+
+// A->B->C->A trade, settled once every participant has deposited their NFT
+#[cw_serde]
+pub struct Ring {
+    pub id: u64,
+    pub proposer: Addr,
+    // the last leg's NFT goes to the first participant
+    pub legs: Vec<RingLeg>,
+    pub deposit_deadline: Timestamp,
+}
+
+// why the marketplace is holding an NFT
+#[cw_serde]
+pub enum CustodyReason {
+    Listing {},
+    Trade { asked_id: String },
+    MixedOffer { asked_id: String },
+    Swap { asked_ids: Vec<String> },
+    DirectTrade { collection: Addr, asked_id: String },
+    Ring { id: u64 },
+}
+
+#[cw_serde]
+pub struct Custody {
+    pub collection: Addr,
+    pub token_id: String,
+    // gets the NFT back if nothing settles
+    pub depositor: Addr,
+    pub reason: CustodyReason,
+}
+
+#[cw_serde]
+pub enum SaleKind {
+    BuyNow,
+    Offer,
+    MixedOffer,
+    // traded for NFTs, through a trade, swap, direct trade or ring
+    Swap,
+}
+
+// a listing sold or traded away, kept after the listing is gone
+#[cw_serde]
+pub struct Sale {
+    pub id: u64,
+    pub token_id: String,
+    pub seller: Addr,
+    pub buyer: Addr,
+    pub price: Uint256,
+    // unset for swaps, which have no price
+    pub currency: Option<CoinType>,
+    pub kind: SaleKind,
+    pub time: Timestamp,
+    pub height: u64,
+}
+
+// running totals behind the collection stats
+#[cw_serde]
+#[derive(Default)]
+pub struct Stats {
+    pub open_offers: u64,
+    pub sale_count: u64,
 }
 
 #[cw_serde]
@@ -38,7 +212,318 @@ pub enum CoinType {
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const LISTINGS: Map<String, Listing> = Map::new("listings"); // (token_id)
-pub const TRADES: Map<(String, String), Trade> = Map::new("trades"); // (token_id, address)
-pub const OFFERS: Map<(String, String), Offer> = Map::new("offers"); // (token_id, address)
+pub const DIRECT_TRADES: Map<(String, String, String), DirectTrade> = Map::new("direct_trades"); // (collection, token_id, address)
+pub const SWAPS: Map<(String, String), Swap> = Map::new("swaps"); // (first asked token_id, address)
+pub const SWAP_TARGETS: Map<(String, String), String> = Map::new("swap_targets"); // (asked token_id, address) -> first asked token_id
+pub const MIXED_OFFERS: Map<(String, String), MixedOffer> = Map::new("mixed_offers"); // (token_id, address)
 pub const LISTING_COUNTER: Item<u128> = Item::new("listing_counter");
+pub const CLOSED_LISTINGS: Map<String, Empty> = Map::new("closed_listings"); // (token_id) offers/trades left to settle
+pub const BALANCES: Map<(String, String), Uint256> = Map::new("balances"); // (address, denom or cw20 address)
+pub const ESCROWED: Map<String, Uint256> = Map::new("escrowed"); // (denom or cw20 address) funds backing open offers
+pub const CREDITED: Map<String, Uint256> = Map::new("credited"); // (denom or cw20 address) sum of BALANCES
+pub const OFFERED_FUNDS: Map<(String, String), Uint256> = Map::new("offered_funds"); // (address, denom or cw20 address) part of ESCROWED backing the address's offers
+pub const COLLECTIONS: Map<String, Empty> = Map::new("collections"); // (cw721 address) collections accepted in escrow
+pub const RINGS: Map<u64, Ring> = Map::new("rings"); // (ring id)
+pub const RING_COUNTER: Item<u64> = Item::new("ring_counter");
+pub const CUSTODY: Map<(String, String), Custody> = Map::new("custody"); // (collection, token_id)
+pub const STATS: Item<Stats> = Item::new("stats");
+pub const SALE_COUNTER: Item<u64> = Item::new("sale_counter");
+pub const VOLUME: Map<String, Uint256> = Map::new("volume"); // (currency) sum of the sale prices in it
+
+/// Index key of the seller of an offer or trade. Those without a seller are
+/// left out of the index, so the empty key is never stored.
+pub fn seller_key(seller: &Option<Addr>) -> String {
+    seller.as_ref().map(Addr::to_string).unwrap_or_default()
+}
+
+/// A `MultiIndex` holding only the items `filter` keeps. It reads like the
+/// index it wraps.
+pub struct FilteredIndex<'a, IK, T, PK> {
+    pub index: MultiIndex<'a, IK, T, PK>,
+    filter: fn(&T) -> bool,
+}
+
+impl<'a, IK, T, PK> FilteredIndex<'a, IK, T, PK> {
+    pub fn new(index: MultiIndex<'a, IK, T, PK>, filter: fn(&T) -> bool) -> Self {
+        FilteredIndex { index, filter }
+    }
+}
+
+impl<'a, IK, T, PK> Deref for FilteredIndex<'a, IK, T, PK> {
+    type Target = MultiIndex<'a, IK, T, PK>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.index
+    }
+}
+
+impl<'a, IK, T, PK> Index<T> for FilteredIndex<'a, IK, T, PK>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    MultiIndex<'a, IK, T, PK>: Index<T>,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
+        if (self.filter)(data) {
+            self.index.save(store, pk, data)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StdResult<()> {
+        if (self.filter)(old_data) {
+            self.index.remove(store, pk, old_data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Index key of a currency.
+pub fn coin_key(coin: &CoinType) -> String {
+    match coin {
+        CoinType::Native => "native".to_string(),
+        CoinType::Cw20 => "cw20".to_string(),
+    }
+}
+
+/// Index key of the currencies a listing takes, `any` when it takes both.
+pub fn listing_coin_key(currency: &Option<CoinType>) -> String {
+    currency
+        .as_ref()
+        .map_or_else(|| ANY_COIN.to_string(), coin_key)
+}
+
+pub const ANY_COIN: &str = "any";
+
+/// Big-endian bytes of a price, so index keys sort in price order.
+pub fn price_key(price: Uint256) -> Vec<u8> {
+    price.to_be_bytes().to_vec()
+}
+
+pub struct ListingIndexes<'a> {
+    pub owner: MultiIndex<'a, String, Listing, String>,
+    pub price: MultiIndex<'a, (String, Vec<u8>), Listing, String>,
+    pub owner_price: MultiIndex<'a, OwnerPriceKey, Listing, String>,
+    pub buy_now_price: FilteredIndex<'a, (String, Vec<u8>), Listing, String>,
+}
+
+// (owner, currency, price), a seller's listings by price per currency
+pub type OwnerPriceKey = (String, String, Vec<u8>);
+
+impl<'a> IndexList<Listing> for ListingIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Listing>> + '_> {
+        let v: Vec<&dyn Index<Listing>> = vec![
+            &self.owner,
+            &self.price,
+            &self.owner_price,
+            &self.buy_now_price,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+// (token_id)
+pub fn listings<'a>() -> IndexedMap<'a, String, Listing, ListingIndexes<'a>> {
+    let indexes = ListingIndexes {
+        owner: MultiIndex::new(
+            |_pk, listing: &Listing| listing.owner.to_string(),
+            "listings",
+            "listings__owner",
+        ),
+        price: MultiIndex::new(
+            |_pk, listing: &Listing| {
+                (
+                    listing_coin_key(&listing.currency),
+                    price_key(listing.price),
+                )
+            },
+            "listings",
+            "listings__currency_price",
+        ),
+        owner_price: MultiIndex::new(
+            |_pk, listing: &Listing| {
+                (
+                    listing.owner.to_string(),
+                    listing_coin_key(&listing.currency),
+                    price_key(listing.price),
+                )
+            },
+            "listings",
+            "listings__owner_currency_price",
+        ),
+        // the listings that can be bought at their price, for the floor
+        buy_now_price: FilteredIndex::new(
+            MultiIndex::new(
+                |_pk, listing: &Listing| {
+                    (
+                        listing_coin_key(&listing.currency),
+                        price_key(listing.price),
+                    )
+                },
+                "listings",
+                "listings__buy_now_price",
+            ),
+            |listing| {
+                matches!(
+                    listing.policy,
+                    AcceptancePolicy::Flexible { buy_now: true, .. }
+                )
+            },
+        ),
+    };
+    IndexedMap::new("listings", indexes)
+}
+
+// a listing as stored by the first release, read by migrate
+#[derive(Serialize, Deserialize)]
+pub struct StoredListing {
+    pub nft_id: String,
+    pub price: Uint256,
+    pub owner: Addr,
+    pub tradeable: bool,
+}
+
+pub const STORED_LISTINGS: Map<String, StoredListing> = Map::new("listings");
+
+pub struct TradeIndexes<'a> {
+    pub trader: MultiIndex<'a, String, Trade, (String, String)>,
+    pub seller: FilteredIndex<'a, String, Trade, (String, String)>,
+}
+
+impl<'a> IndexList<Trade> for TradeIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Trade>> + '_> {
+        let v: Vec<&dyn Index<Trade>> = vec![&self.trader, &self.seller];
+        Box::new(v.into_iter())
+    }
+}
+
+// (token_id, address), trades on a token are read through its prefix
+pub fn trades<'a>() -> IndexedMap<'a, (String, String), Trade, TradeIndexes<'a>> {
+    let indexes = TradeIndexes {
+        trader: MultiIndex::new(
+            |_pk, trade: &Trade| trade.trader.to_string(),
+            "trades",
+            "trades__trader",
+        ),
+        seller: FilteredIndex::new(
+            MultiIndex::new(
+                |_pk, trade: &Trade| seller_key(&trade.seller),
+                "trades",
+                "trades__seller",
+            ),
+            |trade| trade.seller.is_some(),
+        ),
+    };
+    IndexedMap::new("trades", indexes)
+}
+
+pub struct OfferIndexes<'a> {
+    pub offerer: MultiIndex<'a, String, Offer, (String, String)>,
+    pub listed_amount: FilteredIndex<'a, (String, Vec<u8>), Offer, (String, String)>,
+    pub seller: FilteredIndex<'a, String, Offer, (String, String)>,
+    pub token_amount: MultiIndex<'a, TokenAmountKey, Offer, (String, String)>,
+}
+
+// (token_id, currency, amount), offers on a token ranked per currency
+pub type TokenAmountKey = (String, String, Vec<u8>);
+
+impl<'a> IndexList<Offer> for OfferIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Offer>> + '_> {
+        let v: Vec<&dyn Index<Offer>> = vec![
+            &self.offerer,
+            &self.listed_amount,
+            &self.seller,
+            &self.token_amount,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+// (token_id, address), offers on a token are read through its prefix
+pub fn offers<'a>() -> IndexedMap<'a, (String, String), Offer, OfferIndexes<'a>> {
+    let indexes = OfferIndexes {
+        offerer: MultiIndex::new(
+            |_pk, offer: &Offer| offer.offerer.to_string(),
+            "offers",
+            "offers__offerer",
+        ),
+        // (currency, amount) of the offers on listed NFTs
+        listed_amount: FilteredIndex::new(
+            MultiIndex::new(
+                |_pk, offer: &Offer| {
+                    (
+                        coin_key(&offer.amount_type),
+                        price_key(offer.amount_offered),
+                    )
+                },
+                "offers",
+                "offers__listed_amount",
+            ),
+            |offer| offer.seller.is_some(),
+        ),
+        seller: FilteredIndex::new(
+            MultiIndex::new(
+                |_pk, offer: &Offer| seller_key(&offer.seller),
+                "offers",
+                "offers__seller",
+            ),
+            |offer| offer.seller.is_some(),
+        ),
+        token_amount: MultiIndex::new(
+            |_pk, offer: &Offer| {
+                (
+                    offer.asked_id.clone(),
+                    coin_key(&offer.amount_type),
+                    price_key(offer.amount_offered),
+                )
+            },
+            "offers",
+            "offers__token_amount",
+        ),
+    };
+    IndexedMap::new("offers", indexes)
+}
+
+pub struct SaleIndexes<'a> {
+    pub token: MultiIndex<'a, String, Sale, u64>,
+    pub seller: MultiIndex<'a, String, Sale, u64>,
+    pub buyer: MultiIndex<'a, String, Sale, u64>,
+    pub priced: FilteredIndex<'a, String, Sale, u64>,
+}
+
+impl<'a> IndexList<Sale> for SaleIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Sale>> + '_> {
+        let v: Vec<&dyn Index<Sale>> = vec![&self.token, &self.seller, &self.buyer, &self.priced];
+        Box::new(v.into_iter())
+    }
+}
+
+// (sale id)
+pub fn sales<'a>() -> IndexedMap<'a, u64, Sale, SaleIndexes<'a>> {
+    let indexes = SaleIndexes {
+        token: MultiIndex::new(
+            |_pk, sale: &Sale| sale.token_id.clone(),
+            "sales",
+            "sales__token",
+        ),
+        seller: MultiIndex::new(
+            |_pk, sale: &Sale| sale.seller.to_string(),
+            "sales",
+            "sales__seller",
+        ),
+        buyer: MultiIndex::new(
+            |_pk, sale: &Sale| sale.buyer.to_string(),
+            "sales",
+            "sales__buyer",
+        ),
+        // (token_id) sales with a price, swaps left out
+        priced: FilteredIndex::new(
+            MultiIndex::new(
+                |_pk, sale: &Sale| sale.token_id.clone(),
+                "sales",
+                "sales__priced",
+            ),
+            |sale| sale.kind != SaleKind::Swap,
+        ),
+    };
+    IndexedMap::new("sales", indexes)
+}
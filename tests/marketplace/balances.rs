@@ -0,0 +1,70 @@
+use cosmwasm_std::Uint256;
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+
+use gecko_party_marketplace::msg::ExecuteMsg;
+use gecko_party_marketplace::state::CoinType;
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, offerer, Suite, BUYER, FUNDS, SELLER};
+
+fn withdraw(suite: &mut Suite, sender: &str, amount: Option<u128>) -> AnyResult<AppResponse> {
+    suite.execute(
+        sender,
+        &ExecuteMsg::Withdraw {
+            asset: CoinType::Native,
+            amount: amount.map(Uint256::from),
+        },
+        &[],
+    )
+}
+
+#[test]
+fn proceeds_and_refunds_wait_for_withdraw() {
+    let mut suite = Suite::new();
+    let alice = offerer(0);
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.offer(&alice, "1", 40).unwrap();
+
+    suite.buy(BUYER, "1", 100).unwrap();
+    assert_eq!(suite.balance(SELLER), Uint256::from(100u128));
+    assert_eq!(suite.balance(&alice), Uint256::from(40u128));
+    assert_eq!(suite.bank(SELLER), FUNDS);
+    assert_eq!(suite.bank(&alice), FUNDS - 40);
+
+    withdraw(&mut suite, SELLER, None).unwrap();
+    assert_eq!(suite.bank(SELLER), FUNDS + 100);
+    assert_eq!(suite.balance(SELLER), Uint256::zero());
+    assert_error(
+        withdraw(&mut suite, SELLER, None),
+        ContractError::InsufficientBalance {
+            balance: Uint256::zero(),
+        },
+    );
+
+    assert_error(
+        withdraw(&mut suite, &alice, Some(41)),
+        ContractError::InsufficientBalance {
+            balance: Uint256::from(40u128),
+        },
+    );
+    withdraw(&mut suite, &alice, Some(15)).unwrap();
+    assert_eq!(suite.balance(&alice), Uint256::from(25u128));
+    assert_eq!(suite.bank(&alice), FUNDS - 40 + 15);
+}
+
+#[test]
+fn push_to_wallets_pays_out_directly() {
+    let mut suite = Suite::with_config(|msg| msg.push_to_wallets = Some(true));
+    let alice = offerer(0);
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.offer(&alice, "1", 40).unwrap();
+
+    suite.buy(BUYER, "1", 100).unwrap();
+    assert_eq!(suite.bank(SELLER), FUNDS + 100);
+    assert_eq!(suite.bank(&alice), FUNDS);
+    assert_eq!(suite.balance(SELLER), Uint256::zero());
+    assert_eq!(suite.balance(&alice), Uint256::zero());
+}
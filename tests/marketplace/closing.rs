@@ -0,0 +1,184 @@
+use cosmwasm_std::Uint256;
+use cw_multi_test::error::AnyResult;
+
+use gecko_party_marketplace::contract::MAX_CLOSE_BATCH;
+use gecko_party_marketplace::msg::{ExecuteMsg, PageResponse, QueryMsg, ReceiveNftMsg};
+use gecko_party_marketplace::state::{Offer, Trade};
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, native, offerer, Suite, BUYER, SELLER, TRADER};
+
+fn offers_on(suite: &Suite, token_id: &str) -> Vec<Offer> {
+    let page: PageResponse<Offer, String> = suite.query(&QueryMsg::GetOffersById {
+        id: token_id.to_string(),
+        start_after: None,
+        limit: Some(100),
+    });
+    page.items
+}
+
+fn trades_on(suite: &Suite, token_id: &str) -> Vec<Trade> {
+    let page: PageResponse<Trade, String> = suite.query(&QueryMsg::GetTradesById {
+        id: token_id.to_string(),
+        start_after: None,
+        limit: Some(100),
+    });
+    page.items
+}
+
+fn process(suite: &mut Suite, token_id: &str) -> AnyResult<()> {
+    process_some(suite, token_id, None)
+}
+
+fn process_some(suite: &mut Suite, token_id: &str, limit: Option<u32>) -> AnyResult<()> {
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::ProcessClosedListing {
+                id: token_id.to_string(),
+                limit,
+            },
+            &[],
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn sale_refunds_offers_in_batches() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    let offers = MAX_CLOSE_BATCH as usize + 2;
+    for i in 0..offers {
+        suite.offer(&offerer(i), "1", 10).unwrap();
+    }
+
+    // the sale refunds a batch, the rest wait for ProcessClosedListing
+    suite.buy(BUYER, "1", 100).unwrap();
+    assert_eq!(suite.nft_owner("1"), BUYER);
+    let pending = offers_on(&suite, "1");
+    assert_eq!(pending.len(), 2);
+    let refunded = (0..offers)
+        .filter(|i| suite.balance(&offerer(*i)) == Uint256::from(10u128))
+        .count();
+    assert_eq!(refunded, MAX_CLOSE_BATCH as usize);
+    for offer in pending {
+        assert_eq!(suite.balance(offer.offerer.as_str()), Uint256::zero());
+    }
+
+    assert_error(
+        suite.list(BUYER, "1", 200, None),
+        ContractError::RefundsPending {},
+    );
+
+    process(&mut suite, "1").unwrap();
+    assert!(offers_on(&suite, "1").is_empty());
+    for i in 0..offers {
+        assert_eq!(suite.balance(&offerer(i)), Uint256::from(10u128));
+    }
+
+    assert_error(process(&mut suite, "1"), ContractError::NotClosed {});
+    suite.list(BUYER, "1", 200, None).unwrap();
+}
+
+#[test]
+fn cancelling_returns_offers_and_trade_nfts() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "t");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.trade(TRADER, "t", "1").unwrap();
+    suite.offer(&offerer(0), "1", 40).unwrap();
+
+    assert_error(
+        suite.execute(
+            BUYER,
+            &ExecuteMsg::CancelListing {
+                id: "1".to_string(),
+            },
+            &[],
+        ),
+        ContractError::Unauthorized {},
+    );
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::CancelListing {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    assert_eq!(suite.nft_owner("1"), SELLER);
+    assert_eq!(suite.nft_owner("t"), TRADER);
+    assert!(offers_on(&suite, "1").is_empty());
+    assert!(trades_on(&suite, "1").is_empty());
+    assert_eq!(suite.balance(&offerer(0)), Uint256::from(40u128));
+    let count: u128 = suite.query(&QueryMsg::GetListingCount {});
+    assert_eq!(count, 0);
+
+    // nothing was left over, so the listing is not waiting for refunds
+    assert_error(process(&mut suite, "1"), ContractError::NotClosed {});
+}
+
+#[test]
+fn batches_count_every_returned_nft() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    for i in 0..MAX_CLOSE_BATCH as usize {
+        suite.offer(&offerer(i), "1", 10).unwrap();
+    }
+    for (offerer, nfts) in [(BUYER, ["a", "b"]), (TRADER, ["c", "d"])] {
+        for id in nfts {
+            suite.mint(offerer, id);
+        }
+        let refs = nfts.iter().map(|id| suite.nft_ref(id)).collect();
+        suite
+            .execute(
+                offerer,
+                &ExecuteMsg::MixedOffer {
+                    target: "1".to_string(),
+                    nfts: refs,
+                },
+                &native(5),
+            )
+            .unwrap();
+        for id in nfts {
+            suite
+                .send_nft(
+                    offerer,
+                    id,
+                    &ReceiveNftMsg::DepositMixedOffer {
+                        target: "1".to_string(),
+                    },
+                )
+                .unwrap();
+        }
+    }
+
+    // the offers fill the closing batch
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::CancelListing {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("a"), suite.market.to_string());
+
+    // each mixed offer sends back two NFTs and the funds, one fits in four
+    process_some(&mut suite, "1", Some(4)).unwrap();
+    assert_eq!(suite.nft_owner("a"), BUYER);
+    assert_eq!(suite.nft_owner("b"), BUYER);
+    assert_eq!(suite.nft_owner("c"), suite.market.to_string());
+
+    // a batch smaller than one proposal still returns it whole
+    process_some(&mut suite, "1", Some(1)).unwrap();
+    assert_eq!(suite.nft_owner("c"), TRADER);
+    assert_eq!(suite.nft_owner("d"), TRADER);
+    assert_error(process(&mut suite, "1"), ContractError::NotClosed {});
+}
@@ -0,0 +1,105 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{CollectionStatsResponse, CurrencyStats, QueryMsg, ReceiveMsg};
+use gecko_party_marketplace::state::CoinType;
+
+use crate::suite::{offerer, Suite, BUYER, SELLER};
+
+fn stats(suite: &Suite) -> CollectionStatsResponse {
+    suite.query(&QueryMsg::CollectionStats {})
+}
+
+fn currency(stats: &CollectionStatsResponse, currency: CoinType) -> &CurrencyStats {
+    stats
+        .currencies
+        .iter()
+        .find(|stats| stats.currency == currency)
+        .unwrap()
+}
+
+fn best_offer(stats: &CurrencyStats) -> Option<(&str, Uint256)> {
+    stats
+        .highest_offer
+        .as_ref()
+        .map(|offer| (offer.asked_id.as_str(), offer.amount_offered))
+}
+
+#[test]
+fn floor_and_best_offer_per_currency() {
+    let mut suite = Suite::new();
+    let alice = offerer(0);
+    for id in ["1", "2", "3", "9"] {
+        suite.mint(SELLER, id);
+    }
+    suite
+        .list(SELLER, "1", 100, Some(CoinType::Native))
+        .unwrap();
+    suite.list(SELLER, "2", 70, None).unwrap();
+    suite.list(SELLER, "3", 60, Some(CoinType::Cw20)).unwrap();
+
+    // "9" is not listed, so its offer is not the best one
+    suite.offer(&alice, "9", 500).unwrap();
+    suite.offer(&alice, "1", 40).unwrap();
+    suite
+        .send_tokens(
+            &alice,
+            30,
+            &ReceiveMsg::Offer {
+                target: "3".to_string(),
+                offered_price: Uint256::from(30u128),
+            },
+        )
+        .unwrap();
+
+    let all = stats(&suite);
+    assert_eq!(all.active_listings, 3);
+    assert_eq!(all.open_offers, 3);
+    let native = currency(&all, CoinType::Native);
+    assert_eq!(native.floor_price, Some(Uint256::from(70u128)));
+    assert_eq!(best_offer(native), Some(("1", Uint256::from(40u128))));
+    let cw20 = currency(&all, CoinType::Cw20);
+    assert_eq!(cw20.floor_price, Some(Uint256::from(60u128)));
+    assert_eq!(best_offer(cw20), Some(("3", Uint256::from(30u128))));
+
+    // once listed, the waiting offer counts
+    suite.list(SELLER, "9", 1000, None).unwrap();
+    let all = stats(&suite);
+    assert_eq!(
+        best_offer(currency(&all, CoinType::Native)),
+        Some(("9", Uint256::from(500u128)))
+    );
+}
+
+#[test]
+fn sales_add_volume_in_their_currency() {
+    let mut suite = Suite::new();
+    for id in ["1", "2", "3"] {
+        suite.mint(SELLER, id);
+    }
+    suite
+        .list(SELLER, "1", 100, Some(CoinType::Native))
+        .unwrap();
+    suite.list(SELLER, "2", 70, None).unwrap();
+    suite.list(SELLER, "3", 60, Some(CoinType::Cw20)).unwrap();
+
+    suite.buy(BUYER, "2", 70).unwrap();
+    suite
+        .send_tokens(
+            BUYER,
+            60,
+            &ReceiveMsg::Buy {
+                id: "3".to_string(),
+            },
+        )
+        .unwrap();
+
+    let all = stats(&suite);
+    assert_eq!(all.sale_count, 2);
+    assert_eq!(all.active_listings, 1);
+    let native = currency(&all, CoinType::Native);
+    assert_eq!(native.volume, Uint256::from(70u128));
+    assert_eq!(native.floor_price, Some(Uint256::from(100u128)));
+    let cw20 = currency(&all, CoinType::Cw20);
+    assert_eq!(cw20.volume, Uint256::from(60u128));
+    assert_eq!(cw20.floor_price, None);
+}
@@ -0,0 +1,139 @@
+use gecko_party_marketplace::msg::{ExecuteMsg, ReceiveNftMsg, RingParticipant};
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, flexible, native, nft_ref, Suite, BUYER, SELLER, TRADER};
+
+#[test]
+fn whitelisted_collections_trade_for_listings() {
+    let mut suite = Suite::new();
+    let lizards = suite.lizards.clone();
+    suite.mint(SELLER, "1");
+    suite.mint_in(&lizards, TRADER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+
+    // only the marketplace collection is listed
+    assert_error(
+        suite.send_nft_in(
+            &lizards,
+            TRADER,
+            "1",
+            &ReceiveNftMsg::NewListing {
+                price: 100u128.into(),
+                policy: flexible(),
+                preferences: None,
+                auto_accept_at: None,
+                currency: None,
+            },
+        ),
+        ContractError::Unauthorized {},
+    );
+
+    suite
+        .send_nft_in(
+            &lizards,
+            TRADER,
+            "1",
+            &ReceiveNftMsg::NewTrade {
+                target: "1".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::AcceptTrade {
+                id: "1".to_string(),
+                trader: TRADER.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner_in(&lizards, "1"), SELLER);
+}
+
+#[test]
+fn other_collections_are_rejected() {
+    let mut suite = Suite::new();
+    let outsiders = suite.new_collection("outsiders");
+    suite.mint(SELLER, "1");
+    suite.mint(BUYER, "2");
+    suite.mint_in(&outsiders, TRADER, "x");
+    suite.list(SELLER, "1", 100, None).unwrap();
+
+    // escrowing through the outside collection itself
+    assert_error(
+        suite.send_nft_in(
+            &outsiders,
+            TRADER,
+            "x",
+            &ReceiveNftMsg::NewTrade {
+                target: "1".to_string(),
+                expires: None,
+            },
+        ),
+        ContractError::Unauthorized {},
+    );
+
+    // naming an outside NFT or collection in a proposal
+    assert_error(
+        suite.execute(
+            TRADER,
+            &ExecuteMsg::MixedOffer {
+                target: "1".to_string(),
+                nfts: vec![nft_ref(&outsiders, "x")],
+            },
+            &native(5),
+        ),
+        ContractError::CollectionNotAllowed {},
+    );
+    assert_error(
+        suite.execute(
+            TRADER,
+            &ExecuteMsg::ProposeSwap {
+                asked_ids: vec!["1".to_string()],
+                offered: vec![nft_ref(&outsiders, "x")],
+            },
+            &[],
+        ),
+        ContractError::CollectionNotAllowed {},
+    );
+    assert_error(
+        suite.execute(
+            TRADER,
+            &ExecuteMsg::ProposeRing {
+                participants: vec![
+                    RingParticipant {
+                        address: TRADER.to_string(),
+                        nft: nft_ref(&outsiders, "x"),
+                    },
+                    RingParticipant {
+                        address: SELLER.to_string(),
+                        nft: suite.nft_ref("3"),
+                    },
+                    RingParticipant {
+                        address: BUYER.to_string(),
+                        nft: suite.nft_ref("2"),
+                    },
+                ],
+            },
+            &[],
+        ),
+        ContractError::CollectionNotAllowed {},
+    );
+
+    suite.mint(TRADER, "a");
+    assert_error(
+        suite.send_nft(
+            TRADER,
+            "a",
+            &ReceiveNftMsg::ProposeDirectTrade {
+                collection: Some(outsiders.to_string()),
+                target: "x".to_string(),
+                expires: None,
+            },
+        ),
+        ContractError::CollectionNotAllowed {},
+    );
+}
@@ -0,0 +1,108 @@
+use gecko_party_marketplace::msg::{ExecuteMsg, PageResponse, QueryMsg, ReceiveNftMsg};
+use gecko_party_marketplace::state::{Custody, CustodyReason};
+
+use crate::suite::{native, Suite, BUYER, SELLER, TRADER};
+
+fn custody(suite: &Suite, token_id: &str) -> Custody {
+    suite.query(&QueryMsg::GetCustody {
+        collection: suite.nft.to_string(),
+        id: token_id.to_string(),
+    })
+}
+
+fn all_custody(suite: &Suite) -> Vec<Custody> {
+    let page: PageResponse<Custody, (String, String)> = suite.query(&QueryMsg::GetAllCustody {
+        start_after: None,
+        limit: None,
+    });
+    page.items
+}
+
+#[test]
+fn every_escrowed_nft_has_a_depositor_and_reason() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(BUYER, "2");
+    for id in ["a", "b", "c"] {
+        suite.mint(TRADER, id);
+    }
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.trade(TRADER, "a", "1").unwrap();
+
+    let mixed = vec![suite.nft_ref("b")];
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::MixedOffer {
+                target: "1".to_string(),
+                nfts: mixed,
+            },
+            &native(5),
+        )
+        .unwrap();
+    suite
+        .send_nft(
+            TRADER,
+            "b",
+            &ReceiveNftMsg::DepositMixedOffer {
+                target: "1".to_string(),
+            },
+        )
+        .unwrap();
+    suite
+        .send_nft(
+            TRADER,
+            "c",
+            &ReceiveNftMsg::ProposeDirectTrade {
+                collection: None,
+                target: "2".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+
+    let listed = custody(&suite, "1");
+    assert_eq!(listed.depositor, SELLER);
+    assert_eq!(listed.reason, CustodyReason::Listing {});
+    let traded = custody(&suite, "a");
+    assert_eq!(traded.depositor, TRADER);
+    assert_eq!(
+        traded.reason,
+        CustodyReason::Trade {
+            asked_id: "1".to_string()
+        }
+    );
+    assert_eq!(
+        custody(&suite, "b").reason,
+        CustodyReason::MixedOffer {
+            asked_id: "1".to_string()
+        }
+    );
+    assert_eq!(
+        custody(&suite, "c").reason,
+        CustodyReason::DirectTrade {
+            collection: suite.nft.clone(),
+            asked_id: "2".to_string()
+        }
+    );
+    assert_eq!(all_custody(&suite).len(), 4);
+
+    // settling the trade releases the listing and the trade, the mixed offer
+    // goes back to its offerer with the close
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::AcceptTrade {
+                id: "1".to_string(),
+                trader: TRADER.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner("a"), SELLER);
+    assert_eq!(suite.nft_owner("b"), TRADER);
+    let left = all_custody(&suite);
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].token_id, "c");
+}
@@ -0,0 +1,146 @@
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+
+use gecko_party_marketplace::msg::{ExecuteMsg, QueryMsg, ReceiveNftMsg};
+use gecko_party_marketplace::state::DirectTrade;
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, Suite, BUYER, SELLER, TRADER};
+
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "a");
+    suite
+}
+
+fn propose(suite: &mut Suite, trader: &str, offered_id: &str) -> AnyResult<AppResponse> {
+    suite.send_nft(
+        trader,
+        offered_id,
+        &ReceiveNftMsg::ProposeDirectTrade {
+            collection: None,
+            target: "1".to_string(),
+            expires: None,
+        },
+    )
+}
+
+fn direct_msg(suite: &Suite, accept: bool) -> ExecuteMsg {
+    let (collection, id, trader) = (suite.nft.to_string(), "1".to_string(), TRADER.to_string());
+    if accept {
+        ExecuteMsg::AcceptDirectTrade {
+            collection,
+            id,
+            trader,
+        }
+    } else {
+        ExecuteMsg::RejectDirectTrade {
+            collection,
+            id,
+            trader,
+        }
+    }
+}
+
+#[test]
+fn the_owner_accepts_by_sending_the_nft() {
+    let mut suite = setup();
+    propose(&mut suite, TRADER, "a").unwrap();
+    let direct: DirectTrade = suite.query(&QueryMsg::GetDirectTrade {
+        collection: suite.nft.to_string(),
+        id: "1".to_string(),
+        trader: TRADER.to_string(),
+    });
+    assert_eq!(direct.owner, SELLER);
+    assert_eq!(suite.nft_owner("a"), suite.market.to_string());
+
+    suite
+        .send_nft(
+            SELLER,
+            "1",
+            &ReceiveNftMsg::AcceptDirectTrade {
+                trader: TRADER.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner("a"), SELLER);
+}
+
+#[test]
+fn the_owner_accepts_an_approved_nft() {
+    let mut suite = setup();
+    propose(&mut suite, TRADER, "a").unwrap();
+    let accept = direct_msg(&suite, true);
+
+    assert_error(
+        suite.execute(BUYER, &accept, &[]),
+        ContractError::Unauthorized {},
+    );
+    suite.approve(SELLER, "1");
+    suite.execute(SELLER, &accept, &[]).unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner("a"), SELLER);
+}
+
+#[test]
+fn rejecting_and_cancelling_return_the_offered_nft() {
+    let mut suite = setup();
+    propose(&mut suite, TRADER, "a").unwrap();
+    let reject = direct_msg(&suite, false);
+    assert_error(
+        suite.execute(TRADER, &reject, &[]),
+        ContractError::Unauthorized {},
+    );
+    suite.execute(SELLER, &reject, &[]).unwrap();
+    assert_eq!(suite.nft_owner("a"), TRADER);
+
+    propose(&mut suite, TRADER, "a").unwrap();
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::CancelDirectTrade {
+                collection: suite.nft.to_string(),
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("a"), TRADER);
+    assert_eq!(suite.nft_owner("1"), SELLER);
+}
+
+#[test]
+fn own_and_escrowed_nfts_cannot_be_asked() {
+    let mut suite = setup();
+    suite.mint(SELLER, "b");
+    assert_error(
+        propose(&mut suite, SELLER, "b"),
+        ContractError::Unauthorized {},
+    );
+
+    suite.list(SELLER, "1", 100, None).unwrap();
+    assert_error(
+        propose(&mut suite, TRADER, "a"),
+        ContractError::NftListed {},
+    );
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::CancelListing {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // held for a trade on another listing
+    suite.mint(BUYER, "z");
+    suite.list(BUYER, "z", 100, None).unwrap();
+    suite.trade(SELLER, "1", "z").unwrap();
+    assert_error(
+        propose(&mut suite, TRADER, "a"),
+        ContractError::AlreadyInCustody {},
+    );
+}
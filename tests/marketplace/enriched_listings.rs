@@ -0,0 +1,56 @@
+use gecko_party_marketplace::contract::MAX_ENRICHED_QUERY_LIMIT;
+use gecko_party_marketplace::msg::{EnrichedListing, PageResponse, QueryMsg};
+
+use crate::suite::{traits, Suite, SELLER};
+
+#[test]
+fn listings_carry_metadata_and_holder() {
+    let mut suite = Suite::new();
+    let nft = suite.nft.clone();
+    let gold = traits(&[("skin", "gold")]);
+    suite.mint_with(&nft, SELLER, "1", Some(gold.clone()));
+    suite.mint(SELLER, "2");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.list(SELLER, "2", 50, None).unwrap();
+
+    let listing: EnrichedListing = suite.query(&QueryMsg::GetEnrichedListing {
+        id: "1".to_string(),
+    });
+    assert_eq!(listing.listing.nft_id, "1");
+    assert_eq!(listing.listing.owner.as_str(), SELLER);
+    assert_eq!(listing.extension, Some(gold));
+    assert_eq!(listing.token_uri, None);
+    assert_eq!(listing.current_owner, Some(suite.market.to_string()));
+
+    let page: PageResponse<EnrichedListing, String> = suite.query(&QueryMsg::GetEnrichedListings {
+        start_after: Some("1".to_string()),
+        limit: None,
+    });
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].listing.nft_id, "2");
+    assert_eq!(page.items[0].extension, None);
+    assert_eq!(page.items[0].current_owner, Some(suite.market.to_string()));
+}
+
+#[test]
+fn pages_are_capped() {
+    let mut suite = Suite::new();
+    let listed = MAX_ENRICHED_QUERY_LIMIT + 2;
+    for i in 0..listed {
+        let id = format!("{i:02}");
+        suite.mint(SELLER, &id);
+        suite.list(SELLER, &id, 10, None).unwrap();
+    }
+
+    let page: PageResponse<EnrichedListing, String> = suite.query(&QueryMsg::GetEnrichedListings {
+        start_after: None,
+        limit: Some(100),
+    });
+    assert_eq!(page.items.len(), MAX_ENRICHED_QUERY_LIMIT as usize);
+    let rest: PageResponse<EnrichedListing, String> = suite.query(&QueryMsg::GetEnrichedListings {
+        start_after: page.next_start_after,
+        limit: Some(100),
+    });
+    assert_eq!(rest.items.len(), 2);
+    assert_eq!(rest.next_start_after, None);
+}
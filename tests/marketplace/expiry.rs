@@ -0,0 +1,114 @@
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+use cw_utils::Expiration;
+
+use gecko_party_marketplace::msg::{ExecuteMsg, PageResponse, QueryMsg, ReceiveNftMsg};
+use gecko_party_marketplace::state::Trade;
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, attribute, offerer, Suite, SELLER, TRADER};
+
+fn trade_until(
+    suite: &mut Suite,
+    trader: &str,
+    offered_id: &str,
+    expires: Expiration,
+) -> AnyResult<AppResponse> {
+    suite.send_nft(
+        trader,
+        offered_id,
+        &ReceiveNftMsg::NewTrade {
+            target: "1".to_string(),
+            expires: Some(expires),
+        },
+    )
+}
+
+fn expire(suite: &mut Suite, start_after: Option<(String, String)>) -> AppResponse {
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::ExpireTrades {
+                start_after,
+                limit: Some(2),
+            },
+            &[],
+        )
+        .unwrap()
+}
+
+#[test]
+fn expired_trades_cannot_be_made_or_accepted() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "a");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    let height = suite.app.block_info().height;
+
+    assert_error(
+        trade_until(&mut suite, TRADER, "a", Expiration::AtHeight(height)),
+        ContractError::TradeExpired {},
+    );
+    trade_until(&mut suite, TRADER, "a", Expiration::AtHeight(height + 5)).unwrap();
+
+    suite.app.update_block(|block| block.height += 5);
+    assert_error(
+        suite.execute(
+            SELLER,
+            &ExecuteMsg::AcceptTrade {
+                id: "1".to_string(),
+                trader: TRADER.to_string(),
+            },
+            &[],
+        ),
+        ContractError::TradeExpired {},
+    );
+    assert_eq!(suite.nft_owner("a"), suite.market.to_string());
+}
+
+#[test]
+fn the_crank_returns_expired_nfts_page_by_page() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    let height = suite.app.block_info().height;
+    for (i, expires) in [
+        Expiration::AtHeight(height + 5),
+        Expiration::Never {},
+        Expiration::AtHeight(height + 5),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let id = format!("t{i}");
+        suite.mint(&offerer(i), &id);
+        trade_until(&mut suite, &offerer(i), &id, expires).unwrap();
+    }
+
+    // nothing has expired yet, but the scan still moves on
+    let res = expire(&mut suite, None);
+    assert_eq!(attribute(&res, "expired"), Some("0".to_string()));
+
+    suite.app.update_block(|block| block.height += 5);
+    let res = expire(&mut suite, None);
+    assert_eq!(attribute(&res, "expired"), Some("1".to_string()));
+    assert_eq!(
+        attribute(&res, "next_start_after_id"),
+        Some("1".to_string())
+    );
+    assert_eq!(attribute(&res, "next_start_after_trader"), Some(offerer(1)));
+    assert_eq!(suite.nft_owner("t0"), offerer(0));
+
+    let res = expire(&mut suite, Some(("1".to_string(), offerer(1))));
+    assert_eq!(attribute(&res, "expired"), Some("1".to_string()));
+    assert_eq!(attribute(&res, "next_start_after_id"), None);
+    assert_eq!(suite.nft_owner("t2"), offerer(2));
+
+    let left: PageResponse<Trade, String> = suite.query(&QueryMsg::GetTradesById {
+        id: "1".to_string(),
+        start_after: None,
+        limit: None,
+    });
+    assert_eq!(left.items.len(), 1);
+    assert_eq!(left.items[0].trader, offerer(1));
+}
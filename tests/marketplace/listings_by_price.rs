@@ -0,0 +1,177 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{PageResponse, QueryMsg};
+use gecko_party_marketplace::state::{CoinType, Listing};
+
+use crate::suite::{Suite, BUYER, SELLER};
+
+type Cursor = (Uint256, String);
+
+// "d" and "c" take either currency; three listings share a price of 70
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    for (owner, id, price, currency) in [
+        (SELLER, "a", 50, Some(CoinType::Native)),
+        (SELLER, "b", 70, Some(CoinType::Cw20)),
+        (BUYER, "c", 70, None),
+        (SELLER, "d", 30, None),
+        (BUYER, "e", 90, Some(CoinType::Native)),
+        (SELLER, "f", 70, Some(CoinType::Native)),
+    ] {
+        suite.mint(owner, id);
+        suite.list(owner, id, price, currency).unwrap();
+    }
+    suite
+}
+
+#[derive(Default)]
+struct Filter {
+    min_price: Option<u128>,
+    max_price: Option<u128>,
+    currency: Option<CoinType>,
+    seller: Option<&'static str>,
+    descending: bool,
+    start_after: Option<Cursor>,
+    limit: Option<u32>,
+}
+
+fn by_price(suite: &Suite, filter: Filter) -> (Vec<String>, Option<Cursor>) {
+    let page: PageResponse<Listing, Cursor> = suite.query(&QueryMsg::GetListingsByPrice {
+        min_price: filter.min_price.map(Uint256::from),
+        max_price: filter.max_price.map(Uint256::from),
+        currency: filter.currency,
+        seller: filter.seller.map(str::to_string),
+        descending: Some(filter.descending),
+        start_after: filter.start_after,
+        limit: filter.limit,
+    });
+    let ids = page.items.into_iter().map(|l| l.nft_id).collect();
+    (ids, page.next_start_after)
+}
+
+fn cursor(price: u128, id: &str) -> Option<Cursor> {
+    Some((Uint256::from(price), id.to_string()))
+}
+
+#[test]
+fn every_currency_sorted_both_ways() {
+    let suite = setup();
+    let (ids, _) = by_price(&suite, Filter::default());
+    assert_eq!(ids, vec!["d", "a", "b", "c", "f", "e"]);
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            descending: true,
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["e", "f", "c", "b", "a", "d"]);
+}
+
+#[test]
+fn a_currency_includes_listings_taking_any() {
+    let suite = setup();
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            currency: Some(CoinType::Native),
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["d", "a", "c", "f", "e"]);
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            currency: Some(CoinType::Cw20),
+            descending: true,
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["c", "b", "d"]);
+}
+
+#[test]
+fn bounds_and_seller_narrow_the_range() {
+    let suite = setup();
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            min_price: Some(50),
+            max_price: Some(70),
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["a", "b", "c", "f"]);
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            min_price: Some(70),
+            descending: true,
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["e", "f", "c", "b"]);
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            seller: Some(SELLER),
+            max_price: Some(69),
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["d", "a"]);
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            seller: Some(BUYER),
+            currency: Some(CoinType::Cw20),
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["c"]);
+}
+
+#[test]
+fn pages_continue_after_price_and_id() {
+    let suite = setup();
+    let page = |start_after, descending| {
+        by_price(
+            &suite,
+            Filter {
+                descending,
+                start_after,
+                limit: Some(2),
+                ..Filter::default()
+            },
+        )
+    };
+
+    // the cursor sits between listings of the same price
+    let (ids, next) = page(None, false);
+    assert_eq!(ids, vec!["d", "a"]);
+    assert_eq!(next, cursor(50, "a"));
+    let (ids, next) = page(next, false);
+    assert_eq!(ids, vec!["b", "c"]);
+    assert_eq!(next, cursor(70, "c"));
+    let (ids, next) = page(next, false);
+    assert_eq!(ids, vec!["f", "e"]);
+    assert_eq!(next, None);
+
+    let (ids, next) = page(cursor(70, "c"), true);
+    assert_eq!(ids, vec!["b", "a"]);
+    assert_eq!(next, cursor(50, "a"));
+
+    // the cursor takes over from the bound on its side of the range
+    let (ids, _) = by_price(
+        &suite,
+        Filter {
+            min_price: Some(40),
+            max_price: Some(80),
+            currency: Some(CoinType::Native),
+            descending: true,
+            start_after: cursor(70, "f"),
+            ..Filter::default()
+        },
+    );
+    assert_eq!(ids, vec!["c", "a"]);
+}
@@ -0,0 +1,26 @@
+mod suite;
+
+mod balances;
+mod closing;
+mod collection_stats;
+mod collections;
+mod custody;
+mod direct_trades;
+mod enriched_listings;
+mod expiry;
+mod listings_by_price;
+mod migrate;
+mod mixed_offers;
+mod offer_queries;
+mod offer_rules;
+mod offers_by_seller;
+mod pagination;
+mod portfolio;
+mod price_history;
+mod reject_trades;
+mod rings;
+mod sales;
+mod simulate;
+mod solvency;
+mod swaps;
+mod trade_preferences;
@@ -0,0 +1,263 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env};
+use cosmwasm_std::{from_json, Addr, Order, StdResult, Uint256};
+use cw_utils::Expiration;
+
+use gecko_party_marketplace::contract::{self, CONTRACT_NAME, CONTRACT_VERSION, NATIVE_DENOM};
+use gecko_party_marketplace::msg::{MigrateMsg, PageResponse, QueryMsg};
+use gecko_party_marketplace::state::{
+    listings, offers, trades, AcceptancePolicy, CoinType, Custody, CustodyReason, Listing, Stats,
+    COLLECTIONS, CONFIG, CUSTODY, ESCROWED, OFFERED_FUNDS, STATS,
+};
+use gecko_party_marketplace::ContractError;
+
+// the layout of the first release
+mod baseline {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{Addr, Uint256};
+    use cw_storage_plus::{Item, Map};
+
+    use gecko_party_marketplace::state::CoinType;
+
+    #[cw_serde]
+    pub struct Config {
+        pub cw721_address: Addr,
+        pub cw20_address: Addr,
+    }
+
+    #[cw_serde]
+    pub struct Listing {
+        pub nft_id: String,
+        pub price: Uint256,
+        pub owner: Addr,
+        pub tradeable: bool,
+    }
+
+    #[cw_serde]
+    pub struct Trade {
+        pub asked_id: String,
+        pub to_trade_id: String,
+        pub trader: Addr,
+    }
+
+    #[cw_serde]
+    pub struct Offer {
+        pub asked_id: String,
+        pub offerer: Addr,
+        pub amount_offered: Uint256,
+        pub amount_type: CoinType,
+    }
+
+    pub const CONFIG: Item<Config> = Item::new("config");
+    pub const LISTINGS: Map<String, Listing> = Map::new("listings");
+    pub const TRADES: Map<(String, String), Trade> = Map::new("trades");
+    pub const OFFERS: Map<(String, String), Offer> = Map::new("offers");
+    pub const LISTING_COUNTER: Item<u128> = Item::new("listing_counter");
+}
+
+fn migrate_msg() -> MigrateMsg {
+    MigrateMsg {
+        push_to_wallets: None,
+        collections: Some(vec!["lizards".to_string()]),
+    }
+}
+
+fn policy(trades: bool) -> AcceptancePolicy {
+    AcceptancePolicy::Flexible {
+        buy_now: true,
+        offers: true,
+        min_offer: None,
+        trades,
+    }
+}
+
+#[test]
+fn migrates_the_first_release() {
+    let mut deps = mock_dependencies();
+    let storage = deps.as_mut().storage;
+    let (seller, trader, alice, bob) = (
+        Addr::unchecked("seller"),
+        Addr::unchecked("trader"),
+        Addr::unchecked("alice"),
+        Addr::unchecked("bob"),
+    );
+
+    cw2::set_contract_version(storage, CONTRACT_NAME, "0.1.0").unwrap();
+    baseline::CONFIG
+        .save(
+            storage,
+            &baseline::Config {
+                cw721_address: Addr::unchecked("geckos"),
+                cw20_address: Addr::unchecked("token"),
+            },
+        )
+        .unwrap();
+    for (id, tradeable) in [("1", true), ("2", false)] {
+        baseline::LISTINGS
+            .save(
+                storage,
+                id.to_string(),
+                &baseline::Listing {
+                    nft_id: id.to_string(),
+                    price: Uint256::from(100u128),
+                    owner: seller.clone(),
+                    tradeable,
+                },
+            )
+            .unwrap();
+    }
+    baseline::LISTING_COUNTER.save(storage, &2).unwrap();
+    baseline::TRADES
+        .save(
+            storage,
+            ("1".to_string(), trader.to_string()),
+            &baseline::Trade {
+                asked_id: "1".to_string(),
+                to_trade_id: "t".to_string(),
+                trader: trader.clone(),
+            },
+        )
+        .unwrap();
+    for (asked_id, offerer, amount, amount_type) in [
+        ("1", &alice, 40u128, CoinType::Native),
+        ("2", &bob, 30u128, CoinType::Cw20),
+    ] {
+        baseline::OFFERS
+            .save(
+                storage,
+                (asked_id.to_string(), offerer.to_string()),
+                &baseline::Offer {
+                    asked_id: asked_id.to_string(),
+                    offerer: offerer.clone(),
+                    amount_offered: Uint256::from(amount),
+                    amount_type,
+                },
+            )
+            .unwrap();
+    }
+
+    contract::migrate(deps.as_mut(), mock_env(), migrate_msg()).unwrap();
+    let storage = deps.as_ref().storage;
+    assert_eq!(
+        cw2::get_contract_version(storage).unwrap().version,
+        CONTRACT_VERSION
+    );
+
+    // sellers are still paid straight to their wallet
+    assert!(CONFIG.load(storage).unwrap().push_to_wallets);
+
+    // tradeable listings keep taking trades, and every listing is indexed
+    let listing = listings().load(storage, "1".to_string()).unwrap();
+    assert_eq!(listing.policy, policy(true));
+    assert_eq!(listing.currency, None);
+    let listing = listings().load(storage, "2".to_string()).unwrap();
+    assert_eq!(listing.policy, policy(false));
+    let by_seller: PageResponse<Listing, String> = from_json(
+        contract::query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetListingsBySeller {
+                seller: seller.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(by_seller.items.len(), 2);
+
+    // trades and offers get the seller of their listing
+    let trade = trades()
+        .load(storage, ("1".to_string(), trader.to_string()))
+        .unwrap();
+    assert_eq!(trade.seller, Some(seller.clone()));
+    assert_eq!(trade.offered_collection, None);
+    assert_eq!(trade.expires, Expiration::Never {});
+    let offer = offers()
+        .load(storage, ("2".to_string(), bob.to_string()))
+        .unwrap();
+    assert_eq!(offer.seller, Some(seller.clone()));
+
+    assert!(COLLECTIONS.has(storage, "geckos".to_string()));
+    assert!(COLLECTIONS.has(storage, "lizards".to_string()));
+    assert_eq!(
+        STATS.load(storage).unwrap(),
+        Stats {
+            open_offers: 2,
+            sale_count: 0,
+        }
+    );
+    assert_eq!(
+        ESCROWED.load(storage, NATIVE_DENOM.to_string()).unwrap(),
+        Uint256::from(40u128)
+    );
+    assert_eq!(
+        ESCROWED.load(storage, "token".to_string()).unwrap(),
+        Uint256::from(30u128)
+    );
+    assert_eq!(
+        OFFERED_FUNDS
+            .load(storage, (bob.to_string(), "token".to_string()))
+            .unwrap(),
+        Uint256::from(30u128)
+    );
+
+    let custody: Vec<Custody> = CUSTODY
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, custody)| custody))
+        .collect::<StdResult<_>>()
+        .unwrap();
+    let geckos = Addr::unchecked("geckos");
+    assert_eq!(
+        custody,
+        vec![
+            Custody {
+                collection: geckos.clone(),
+                token_id: "1".to_string(),
+                depositor: seller.clone(),
+                reason: CustodyReason::Listing {},
+            },
+            Custody {
+                collection: geckos.clone(),
+                token_id: "2".to_string(),
+                depositor: seller,
+                reason: CustodyReason::Listing {},
+            },
+            Custody {
+                collection: geckos,
+                token_id: "t".to_string(),
+                depositor: trader,
+                reason: CustodyReason::Trade {
+                    asked_id: "1".to_string()
+                },
+            },
+        ]
+    );
+
+    // the conversion only ever runs once
+    let err = contract::migrate(deps.as_mut(), mock_env(), migrate_msg()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ContractError::CannotMigrate {
+            contract: CONTRACT_NAME.to_string(),
+            version: CONTRACT_VERSION.to_string(),
+        }
+        .to_string()
+    );
+}
+
+#[test]
+fn other_contracts_are_not_migrated() {
+    let mut deps = mock_dependencies();
+    cw2::set_contract_version(deps.as_mut().storage, "crates.io:cw721-base", "0.1.0").unwrap();
+
+    let err = contract::migrate(deps.as_mut(), mock_env(), migrate_msg()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ContractError::CannotMigrate {
+            contract: "crates.io:cw721-base".to_string(),
+            version: "0.1.0".to_string(),
+        }
+        .to_string()
+    );
+}
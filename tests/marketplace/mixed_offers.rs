@@ -0,0 +1,113 @@
+use cosmwasm_std::Uint256;
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+
+use gecko_party_marketplace::contract::MAX_MIXED_OFFER_NFTS;
+use gecko_party_marketplace::msg::{ExecuteMsg, QueryMsg, ReceiveNftMsg};
+use gecko_party_marketplace::state::{CoinType, MixedOffer};
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_covered, assert_error, native, Suite, SELLER, TRADER};
+
+fn propose(suite: &mut Suite, nfts: &[&str], amount: u128) -> AnyResult<AppResponse> {
+    let nfts = nfts.iter().map(|id| suite.nft_ref(id)).collect();
+    suite.execute(
+        TRADER,
+        &ExecuteMsg::MixedOffer {
+            target: "1".to_string(),
+            nfts,
+        },
+        &native(amount),
+    )
+}
+
+fn deposit(suite: &mut Suite, token_id: &str) -> AnyResult<AppResponse> {
+    suite.send_nft(
+        TRADER,
+        token_id,
+        &ReceiveNftMsg::DepositMixedOffer {
+            target: "1".to_string(),
+        },
+    )
+}
+
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    for id in ["a", "b", "c"] {
+        suite.mint(TRADER, id);
+    }
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite
+}
+
+#[test]
+fn accepting_swaps_the_nfts_and_pays_the_seller() {
+    let mut suite = setup();
+    assert_error(
+        propose(&mut suite, &[], 25),
+        ContractError::InvalidNftCount {
+            max: MAX_MIXED_OFFER_NFTS as u32,
+        },
+    );
+    assert_error(
+        propose(&mut suite, &["a", "a"], 25),
+        ContractError::DuplicateNft {},
+    );
+    propose(&mut suite, &["a", "b"], 25).unwrap();
+    assert_covered(&suite.solvency(CoinType::Native), 25, 0);
+
+    deposit(&mut suite, "a").unwrap();
+    assert_error(deposit(&mut suite, "c"), ContractError::UnexpectedNft {});
+    let accept = ExecuteMsg::AcceptMixedOffer {
+        id: "1".to_string(),
+        offerer: TRADER.to_string(),
+    };
+    assert_error(
+        suite.execute(SELLER, &accept, &[]),
+        ContractError::DepositsIncomplete {},
+    );
+
+    deposit(&mut suite, "b").unwrap();
+    let mixed: MixedOffer = suite.query(&QueryMsg::GetMixedOffer {
+        id: "1".to_string(),
+        offerer: TRADER.to_string(),
+    });
+    assert!(mixed.nfts.iter().all(|nft| nft.deposited));
+    assert_error(
+        suite.execute(TRADER, &accept, &[]),
+        ContractError::Unauthorized {},
+    );
+
+    suite.execute(SELLER, &accept, &[]).unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner("a"), SELLER);
+    assert_eq!(suite.nft_owner("b"), SELLER);
+    assert_eq!(suite.nft_owner("c"), TRADER);
+    assert_eq!(suite.balance(SELLER), Uint256::from(25u128));
+    assert_covered(&suite.solvency(CoinType::Native), 0, 25);
+}
+
+#[test]
+fn cancelling_returns_deposits_and_funds() {
+    let mut suite = setup();
+    propose(&mut suite, &["a", "b"], 25).unwrap();
+    deposit(&mut suite, "a").unwrap();
+
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::CancelMixedOffer {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("a"), TRADER);
+    assert_eq!(suite.nft_owner("1"), suite.market.to_string());
+    assert_eq!(suite.balance(TRADER), Uint256::from(25u128));
+    assert_covered(&suite.solvency(CoinType::Native), 0, 25);
+
+    // the offer is gone, so a late deposit has nowhere to go
+    assert!(deposit(&mut suite, "b").is_err());
+}
@@ -0,0 +1,115 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{
+    ExecuteMsg, HighestOfferResponse, PageResponse, QueryMsg, ReceiveMsg,
+};
+use gecko_party_marketplace::state::{CoinType, Offer};
+
+use crate::suite::{offerer, Suite, SELLER};
+
+type Cursor = (Uint256, String);
+
+// "1" takes either currency; offerers 1 and 2 tie at 50
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(SELLER, "2");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.list(SELLER, "2", 100, None).unwrap();
+    for (i, amount) in [(0, 30), (1, 50), (2, 50), (3, 20)] {
+        suite.offer(&offerer(i), "1", amount).unwrap();
+    }
+    suite
+        .send_tokens(
+            &offerer(4),
+            60,
+            &ReceiveMsg::Offer {
+                target: "1".to_string(),
+                offered_price: Uint256::from(60u128),
+            },
+        )
+        .unwrap();
+    suite.offer(&offerer(5), "2", 90).unwrap();
+    suite
+}
+
+fn highest(suite: &Suite) -> HighestOfferResponse {
+    suite.query(&QueryMsg::HighestOffer {
+        id: "1".to_string(),
+    })
+}
+
+fn sorted(
+    suite: &Suite,
+    currency: CoinType,
+    start_after: Option<Cursor>,
+) -> (Vec<(String, Uint256)>, Option<Cursor>) {
+    let page: PageResponse<Offer, Cursor> = suite.query(&QueryMsg::OffersSortedByPrice {
+        id: "1".to_string(),
+        currency,
+        start_after,
+        limit: Some(2),
+    });
+    let offers = page
+        .items
+        .into_iter()
+        .map(|offer| (offer.offerer.to_string(), offer.amount_offered))
+        .collect();
+    (offers, page.next_start_after)
+}
+
+#[test]
+fn the_best_offer_in_each_currency() {
+    let mut suite = setup();
+    let best = highest(&suite);
+    let native = best.native.unwrap();
+    assert_eq!(native.offerer, offerer(2));
+    assert_eq!(native.amount_offered, Uint256::from(50u128));
+    let cw20 = best.cw20.unwrap();
+    assert_eq!(cw20.offerer, offerer(4));
+    assert_eq!(cw20.amount_type, CoinType::Cw20);
+
+    suite
+        .execute(
+            &offerer(2),
+            &ExecuteMsg::CancelOffer {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(highest(&suite).native.unwrap().offerer, offerer(1));
+
+    let none: HighestOfferResponse = suite.query(&QueryMsg::HighestOffer {
+        id: "9".to_string(),
+    });
+    assert_eq!(none.native, None);
+    assert_eq!(none.cw20, None);
+}
+
+#[test]
+fn offers_come_highest_first_page_by_page() {
+    let suite = setup();
+    let (offers, next) = sorted(&suite, CoinType::Native, None);
+    assert_eq!(
+        offers,
+        vec![
+            (offerer(2), Uint256::from(50u128)),
+            (offerer(1), Uint256::from(50u128))
+        ]
+    );
+    assert_eq!(next, Some((Uint256::from(50u128), offerer(1))));
+    let (offers, next) = sorted(&suite, CoinType::Native, next);
+    assert_eq!(
+        offers,
+        vec![
+            (offerer(0), Uint256::from(30u128)),
+            (offerer(3), Uint256::from(20u128))
+        ]
+    );
+    assert_eq!(next, None);
+
+    let (offers, next) = sorted(&suite, CoinType::Cw20, None);
+    assert_eq!(offers, vec![(offerer(4), Uint256::from(60u128))]);
+    assert_eq!(next, None);
+}
@@ -0,0 +1,110 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{ExecuteMsg, ReceiveMsg, ReceiveNftMsg};
+use gecko_party_marketplace::state::{AcceptancePolicy, CoinType};
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_covered, assert_error, offerer, Suite, SELLER};
+
+fn with_rules(currency: Option<CoinType>) -> ReceiveNftMsg {
+    ReceiveNftMsg::NewListing {
+        price: Uint256::from(100u128),
+        policy: AcceptancePolicy::Flexible {
+            buy_now: true,
+            offers: true,
+            min_offer: Some(Uint256::from(50u128)),
+            trades: false,
+        },
+        preferences: None,
+        auto_accept_at: Some(Uint256::from(80u128)),
+        currency,
+    }
+}
+
+#[test]
+fn offers_below_the_floor_are_refused_and_above_the_threshold_settle() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+
+    assert_error(
+        suite.send_nft(SELLER, "1", &with_rules(None)),
+        ContractError::RulesNeedCurrency {},
+    );
+    suite
+        .send_nft(SELLER, "1", &with_rules(Some(CoinType::Native)))
+        .unwrap();
+
+    assert_error(
+        suite.offer(&offerer(0), "1", 40),
+        ContractError::OfferTooLow {
+            min_offer: Uint256::from(50u128),
+        },
+    );
+    suite.offer(&offerer(0), "1", 60).unwrap();
+    assert_eq!(suite.nft_owner("1"), suite.market.to_string());
+
+    // 90 is past the threshold, so the offer settles on the spot and the
+    // other offer is refunded
+    suite.offer(&offerer(1), "1", 90).unwrap();
+    assert_eq!(suite.nft_owner("1"), offerer(1));
+    assert_eq!(suite.balance(SELLER), Uint256::from(90u128));
+    assert_eq!(suite.balance(&offerer(0)), Uint256::from(60u128));
+    assert_covered(&suite.solvency(CoinType::Native), 0, 150);
+}
+
+#[test]
+fn cw20_offers_follow_the_same_rules() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite
+        .send_nft(SELLER, "1", &with_rules(Some(CoinType::Cw20)))
+        .unwrap();
+    let offer = |amount: u128| ReceiveMsg::Offer {
+        target: "1".to_string(),
+        offered_price: Uint256::from(amount),
+    };
+
+    assert_error(
+        suite.send_tokens(&offerer(0), 40, &offer(40)),
+        ContractError::OfferTooLow {
+            min_offer: Uint256::from(50u128),
+        },
+    );
+    suite.send_tokens(&offerer(0), 80, &offer(80)).unwrap();
+    assert_eq!(suite.nft_owner("1"), offerer(0));
+    assert_covered(&suite.solvency(CoinType::Cw20), 0, 80);
+}
+
+#[test]
+fn rules_can_change_on_priced_listings_only() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(SELLER, "2");
+    suite
+        .list(SELLER, "1", 100, Some(CoinType::Native))
+        .unwrap();
+    suite.list(SELLER, "2", 100, None).unwrap();
+    let rules = |id: &str| ExecuteMsg::SetOfferRules {
+        id: id.to_string(),
+        min_offer: Some(Uint256::from(30u128)),
+        auto_accept_at: None,
+    };
+
+    assert_error(
+        suite.execute(&offerer(0), &rules("1"), &[]),
+        ContractError::Unauthorized {},
+    );
+    suite.execute(SELLER, &rules("1"), &[]).unwrap();
+    assert_error(
+        suite.offer(&offerer(0), "1", 20),
+        ContractError::OfferTooLow {
+            min_offer: Uint256::from(30u128),
+        },
+    );
+
+    // without a currency no thresholds can be set later either
+    assert_error(
+        suite.execute(SELLER, &rules("2"), &[]),
+        ContractError::RulesNeedCurrency {},
+    );
+}
@@ -0,0 +1,101 @@
+use gecko_party_marketplace::msg::{ExecuteMsg, PageResponse, QueryMsg};
+use gecko_party_marketplace::state::{Offer, Trade};
+
+use crate::suite::{offerer, Suite, BUYER, SELLER, TRADER};
+
+type Key = (String, String);
+
+fn offers_to(suite: &Suite, seller: &str, start_after: Option<Key>) -> PageResponse<Offer, Key> {
+    suite.query(&QueryMsg::GetOffersBySeller {
+        seller: seller.to_string(),
+        start_after,
+        limit: Some(2),
+    })
+}
+
+fn trades_to(suite: &Suite, seller: &str) -> Vec<Trade> {
+    let page: PageResponse<Trade, Key> = suite.query(&QueryMsg::GetTradesBySeller {
+        seller: seller.to_string(),
+        start_after: None,
+        limit: None,
+    });
+    page.items
+}
+
+fn asked(offers: &[Offer]) -> Vec<(&str, &str)> {
+    offers
+        .iter()
+        .map(|offer| (offer.asked_id.as_str(), offer.offerer.as_str()))
+        .collect()
+}
+
+#[test]
+fn offers_follow_the_listing_owner() {
+    let mut suite = Suite::new();
+    let (alice, bob) = (offerer(0), offerer(1));
+    for id in ["1", "2", "9"] {
+        suite.mint(SELLER, id);
+    }
+    suite.mint(BUYER, "b");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.list(SELLER, "2", 100, None).unwrap();
+    suite.list(BUYER, "b", 100, None).unwrap();
+
+    suite.offer(&alice, "1", 10).unwrap();
+    suite.offer(&bob, "1", 20).unwrap();
+    suite.offer(&alice, "2", 30).unwrap();
+    suite.offer(&alice, "b", 40).unwrap();
+    // not listed, so no seller yet
+    suite.offer(&bob, "9", 50).unwrap();
+
+    let page = offers_to(&suite, SELLER, None);
+    assert_eq!(
+        asked(&page.items),
+        vec![("1", alice.as_str()), ("1", bob.as_str())]
+    );
+    let page = offers_to(&suite, SELLER, page.next_start_after);
+    assert_eq!(asked(&page.items), vec![("2", alice.as_str())]);
+    assert_eq!(page.next_start_after, None);
+    assert_eq!(
+        asked(&offers_to(&suite, BUYER, None).items),
+        vec![("b", alice.as_str())]
+    );
+
+    // listing "9" makes its waiting offer the seller's
+    suite.list(SELLER, "9", 100, None).unwrap();
+    let page = offers_to(&suite, SELLER, Some(("2".to_string(), alice.clone())));
+    assert_eq!(asked(&page.items), vec![("9", bob.as_str())]);
+
+    // and a sale takes them all off
+    suite.buy(BUYER, "1", 100).unwrap();
+    let page = offers_to(&suite, SELLER, None);
+    assert_eq!(
+        asked(&page.items),
+        vec![("2", alice.as_str()), ("9", bob.as_str())]
+    );
+}
+
+#[test]
+fn trades_follow_the_listing_owner() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "a");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.trade(TRADER, "a", "1").unwrap();
+
+    let trades = trades_to(&suite, SELLER);
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].to_trade_id, "a");
+    assert!(trades_to(&suite, BUYER).is_empty());
+
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::CancelListing {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(trades_to(&suite, SELLER).is_empty());
+}
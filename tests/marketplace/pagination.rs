@@ -0,0 +1,104 @@
+use gecko_party_marketplace::contract::{DEFAULT_QUERY_LIMIT, MAX_QUERY_LIMIT};
+use gecko_party_marketplace::msg::{PageResponse, QueryMsg};
+use gecko_party_marketplace::state::{Listing, Trade};
+
+use crate::suite::{offerer, Suite, SELLER, TRADER};
+
+fn listing_page(
+    suite: &Suite,
+    start_after: Option<&str>,
+    limit: Option<u32>,
+) -> (Vec<String>, Option<String>) {
+    let page: PageResponse<Listing, String> = suite.query(&QueryMsg::GetAllListings {
+        start_after: start_after.map(str::to_string),
+        limit,
+    });
+    let ids = page.items.into_iter().map(|l| l.nft_id).collect();
+    (ids, page.next_start_after)
+}
+
+#[test]
+fn pages_continue_after_the_cursor() {
+    let mut suite = Suite::new();
+    for i in 0..25 {
+        let id = format!("t{i:02}");
+        suite.mint(SELLER, &id);
+        suite.list(SELLER, &id, 100, None).unwrap();
+    }
+
+    // 10 by default, continuing right after the cursor
+    let (ids, next) = listing_page(&suite, None, None);
+    assert_eq!(ids.len(), DEFAULT_QUERY_LIMIT as usize);
+    assert_eq!(next.as_deref(), Some("t09"));
+    let (ids, next) = listing_page(&suite, next.as_deref(), None);
+    assert_eq!(ids.first().map(String::as_str), Some("t10"));
+    assert_eq!(next.as_deref(), Some("t19"));
+    let (ids, next) = listing_page(&suite, next.as_deref(), None);
+    assert_eq!(ids.len(), 5);
+    assert_eq!(next, None);
+
+    // a page that ends exactly on the last entry has no cursor
+    let (ids, next) = listing_page(&suite, None, Some(25));
+    assert_eq!(ids.len(), 25);
+    assert_eq!(next, None);
+    let (_, next) = listing_page(&suite, None, Some(24));
+    assert_eq!(next.as_deref(), Some("t23"));
+
+    // a cursor that is not a key starts at the next one
+    let (ids, _) = listing_page(&suite, Some("t05a"), Some(1));
+    assert_eq!(ids, vec!["t06".to_string()]);
+    let (ids, next) = listing_page(&suite, Some("t24"), None);
+    assert!(ids.is_empty());
+    assert_eq!(next, None);
+}
+
+#[test]
+fn limits_are_capped() {
+    let mut suite = Suite::new();
+    let count = MAX_QUERY_LIMIT as usize + 1;
+    for i in 0..count {
+        let id = format!("t{i:03}");
+        suite.mint(SELLER, &id);
+        suite.list(SELLER, &id, 100, None).unwrap();
+    }
+
+    let (ids, next) = listing_page(&suite, None, Some(MAX_QUERY_LIMIT * 2));
+    assert_eq!(ids.len(), MAX_QUERY_LIMIT as usize);
+    let (ids, next) = listing_page(&suite, next.as_deref(), Some(MAX_QUERY_LIMIT * 2));
+    assert_eq!(ids.len(), 1);
+    assert_eq!(next, None);
+}
+
+#[test]
+fn index_queries_page_by_their_own_key() {
+    let mut suite = Suite::new();
+    for i in 0..3 {
+        let (id, offered) = (i.to_string(), format!("t{i}"));
+        suite.mint(SELLER, &id);
+        suite.list(SELLER, &id, 100, None).unwrap();
+        suite.mint(TRADER, &offered);
+        suite.trade(TRADER, &offered, &id).unwrap();
+    }
+    // someone else's trade is not in the trader's pages
+    suite.mint(&offerer(0), "x");
+    suite.trade(&offerer(0), "x", "1").unwrap();
+
+    let page = |start_after: Option<(String, String)>| -> PageResponse<Trade, (String, String)> {
+        suite.query(&QueryMsg::GetTradesByAddress {
+            address: TRADER.to_string(),
+            start_after,
+            limit: Some(2),
+        })
+    };
+    let first = page(None);
+    let asked: Vec<&str> = first.items.iter().map(|t| t.asked_id.as_str()).collect();
+    assert_eq!(asked, vec!["0", "1"]);
+    assert_eq!(
+        first.next_start_after,
+        Some(("1".to_string(), TRADER.to_string()))
+    );
+    let second = page(first.next_start_after.clone());
+    let asked: Vec<&str> = second.items.iter().map(|t| t.asked_id.as_str()).collect();
+    assert_eq!(asked, vec!["2"]);
+    assert_eq!(second.next_start_after, None);
+}
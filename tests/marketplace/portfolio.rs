@@ -0,0 +1,80 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{AssetAmount, ExecuteMsg, PortfolioResponse, QueryMsg};
+use gecko_party_marketplace::state::CoinType;
+
+use crate::suite::{native, offerer, Suite, BUYER, FUNDS, SELLER, TRADER};
+
+fn portfolio(suite: &Suite, address: &str) -> PortfolioResponse {
+    suite.query(&QueryMsg::Portfolio {
+        address: address.to_string(),
+    })
+}
+
+fn amount(amounts: &[AssetAmount], asset: CoinType) -> Uint256 {
+    amounts
+        .iter()
+        .find(|amount| amount.asset == asset)
+        .map(|amount| amount.amount)
+        .unwrap()
+}
+
+#[test]
+fn an_address_sees_both_sides_and_its_funds() {
+    let mut suite = Suite::new();
+    let alice = offerer(0);
+    for id in ["1", "2", "3", "9"] {
+        suite.mint(SELLER, id);
+    }
+    suite.mint(&alice, "m");
+    suite.mint(TRADER, "t");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.list(SELLER, "2", 70, None).unwrap();
+    suite.list(SELLER, "3", 90, None).unwrap();
+
+    suite.offer(&alice, "9", 500).unwrap();
+    suite.offer(&alice, "1", 40).unwrap();
+    suite
+        .execute(
+            &alice,
+            &ExecuteMsg::MixedOffer {
+                target: "1".to_string(),
+                nfts: vec![suite.nft_ref("m")],
+            },
+            &native(25),
+        )
+        .unwrap();
+    suite.trade(TRADER, "t", "3").unwrap();
+    suite.buy(BUYER, "2", 70).unwrap();
+
+    let alices = portfolio(&suite, &alice);
+    assert_eq!(alices.offers_made.items.len(), 2);
+    assert!(alices.listings.items.is_empty());
+    assert_eq!(
+        amount(&alices.escrowed, CoinType::Native),
+        Uint256::from(565u128)
+    );
+    assert_eq!(amount(&alices.escrowed, CoinType::Cw20), Uint256::zero());
+
+    let sellers = portfolio(&suite, SELLER);
+    let listed: Vec<&str> = sellers
+        .listings
+        .items
+        .iter()
+        .map(|listing| listing.nft_id.as_str())
+        .collect();
+    assert_eq!(listed, vec!["1", "3"]);
+    assert_eq!(sellers.listings.next_start_after, None);
+    // the offer on "9" waits for a listing, so it is nobody's yet
+    assert_eq!(sellers.offers_received.items.len(), 1);
+    assert_eq!(sellers.trades_received.items.len(), 1);
+    assert_eq!(
+        amount(&sellers.withdrawable, CoinType::Native),
+        Uint256::from(70u128)
+    );
+    assert_eq!(suite.bank(SELLER), FUNDS);
+
+    let traders = portfolio(&suite, TRADER);
+    assert_eq!(traders.trades_proposed.items.len(), 1);
+    assert_eq!(traders.trades_proposed.items[0].asked_id, "3");
+}
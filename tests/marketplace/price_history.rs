@@ -0,0 +1,139 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{
+    CollectionStatsResponse, ExecuteMsg, PageResponse, QueryMsg, ReceiveNftMsg, RingParticipant,
+};
+use gecko_party_marketplace::state::{Sale, SaleKind};
+
+use crate::suite::{nft_ref, offerer, Suite, BUYER, SELLER, TRADER};
+
+fn price_history(suite: &Suite, limit: Option<u32>) -> Vec<Uint256> {
+    let sales: Vec<Sale> = suite.query(&QueryMsg::PriceHistory {
+        id: "1".to_string(),
+        limit,
+    });
+    sales.into_iter().map(|sale| sale.price).collect()
+}
+
+fn last_sale(suite: &Suite) -> Option<Sale> {
+    suite.query(&QueryMsg::LastSale {
+        id: "1".to_string(),
+    })
+}
+
+fn kinds(suite: &Suite, token_id: &str) -> Vec<SaleKind> {
+    let page: PageResponse<Sale, u64> = suite.query(&QueryMsg::GetSalesByToken {
+        id: token_id.to_string(),
+        start_after: None,
+        limit: None,
+    });
+    page.items.into_iter().map(|sale| sale.kind).collect()
+}
+
+#[test]
+fn swaps_are_recorded_but_have_no_price() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "a");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.buy(BUYER, "1", 100).unwrap();
+
+    // traded away through a listing
+    suite.list(BUYER, "1", 100, None).unwrap();
+    suite.trade(TRADER, "a", "1").unwrap();
+    suite
+        .execute(
+            BUYER,
+            &ExecuteMsg::AcceptTrade {
+                id: "1".to_string(),
+                trader: TRADER.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(last_sale(&suite).unwrap().price, Uint256::from(100u128));
+
+    suite.list(TRADER, "1", 150, None).unwrap();
+    suite.buy(&offerer(0), "1", 150).unwrap();
+    assert_eq!(
+        price_history(&suite, None),
+        vec![Uint256::from(150u128), Uint256::from(100u128)]
+    );
+    assert_eq!(price_history(&suite, Some(1)), vec![Uint256::from(150u128)]);
+    assert_eq!(
+        kinds(&suite, "1"),
+        vec![SaleKind::BuyNow, SaleKind::Swap, SaleKind::BuyNow]
+    );
+
+    let stats: CollectionStatsResponse = suite.query(&QueryMsg::CollectionStats {});
+    assert_eq!(stats.sale_count, 2);
+}
+
+#[test]
+fn direct_trades_and_rings_are_recorded() {
+    let mut suite = Suite::new();
+    let lizards = suite.lizards.clone();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "a");
+    suite.mint(BUYER, "b");
+    suite.mint_in(&lizards, BUYER, "l");
+
+    suite
+        .send_nft(
+            TRADER,
+            "a",
+            &ReceiveNftMsg::ProposeDirectTrade {
+                collection: None,
+                target: "1".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+    suite
+        .send_nft(
+            SELLER,
+            "1",
+            &ReceiveNftMsg::AcceptDirectTrade {
+                trader: TRADER.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(kinds(&suite, "1"), vec![SaleKind::Swap]);
+    assert_eq!(kinds(&suite, "a"), Vec::<SaleKind>::new());
+
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::ProposeRing {
+                participants: vec![
+                    RingParticipant {
+                        address: TRADER.to_string(),
+                        nft: suite.nft_ref("1"),
+                    },
+                    RingParticipant {
+                        address: SELLER.to_string(),
+                        nft: suite.nft_ref("a"),
+                    },
+                    RingParticipant {
+                        address: BUYER.to_string(),
+                        nft: nft_ref(&lizards, "l"),
+                    },
+                ],
+            },
+            &[],
+        )
+        .unwrap();
+    let deposit = ReceiveNftMsg::DepositRing { id: 1 };
+    suite.send_nft(TRADER, "1", &deposit).unwrap();
+    suite.send_nft(SELLER, "a", &deposit).unwrap();
+    suite.send_nft_in(&lizards, BUYER, "l", &deposit).unwrap();
+    assert_eq!(suite.nft_owner("1"), SELLER);
+
+    // only NFTs of the marketplace collection have a history
+    assert_eq!(kinds(&suite, "1"), vec![SaleKind::Swap, SaleKind::Swap]);
+    assert_eq!(kinds(&suite, "a"), vec![SaleKind::Swap]);
+    assert_eq!(kinds(&suite, "l"), Vec::<SaleKind>::new());
+    assert_eq!(last_sale(&suite), None);
+    assert!(price_history(&suite, None).is_empty());
+}
@@ -0,0 +1,86 @@
+use gecko_party_marketplace::contract::MAX_CLOSE_BATCH;
+use gecko_party_marketplace::msg::{ExecuteMsg, PageResponse, QueryMsg};
+use gecko_party_marketplace::state::Trade;
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, attribute, offerer, Suite, SELLER, TRADER};
+
+fn trades_on(suite: &Suite, token_id: &str) -> Vec<Trade> {
+    let page: PageResponse<Trade, String> = suite.query(&QueryMsg::GetTradesById {
+        id: token_id.to_string(),
+        start_after: None,
+        limit: Some(100),
+    });
+    page.items
+}
+
+#[test]
+fn rejecting_returns_the_offered_nft() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(TRADER, "a");
+    suite.mint(&offerer(0), "b");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.trade(TRADER, "a", "1").unwrap();
+    suite.trade(&offerer(0), "b", "1").unwrap();
+
+    let reject = ExecuteMsg::RejectTrade {
+        id: "1".to_string(),
+        trader: TRADER.to_string(),
+    };
+    assert_error(
+        suite.execute(TRADER, &reject, &[]),
+        ContractError::Unauthorized {},
+    );
+    suite.execute(SELLER, &reject, &[]).unwrap();
+
+    assert_eq!(suite.nft_owner("a"), TRADER);
+    let left = trades_on(&suite, "1");
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].trader, offerer(0));
+    // the listing stays open
+    assert_eq!(suite.nft_owner("1"), suite.market.to_string());
+}
+
+#[test]
+fn rejecting_all_works_in_batches() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    let traders = MAX_CLOSE_BATCH as usize + 2;
+    for i in 0..traders {
+        let id = format!("t{i}");
+        suite.mint(&offerer(i), &id);
+        suite.trade(&offerer(i), &id, "1").unwrap();
+    }
+
+    let reject_all = ExecuteMsg::RejectAllTrades {
+        id: "1".to_string(),
+    };
+    assert_error(
+        suite.execute(TRADER, &reject_all, &[]),
+        ContractError::Unauthorized {},
+    );
+
+    let res = suite.execute(SELLER, &reject_all, &[]).unwrap();
+    assert_eq!(
+        attribute(&res, "rejected"),
+        Some(MAX_CLOSE_BATCH.to_string())
+    );
+    assert_eq!(
+        attribute(&res, "trades remaining"),
+        Some("true".to_string())
+    );
+    assert_eq!(trades_on(&suite, "1").len(), 2);
+
+    let res = suite.execute(SELLER, &reject_all, &[]).unwrap();
+    assert_eq!(attribute(&res, "rejected"), Some("2".to_string()));
+    assert_eq!(
+        attribute(&res, "trades remaining"),
+        Some("false".to_string())
+    );
+    assert!(trades_on(&suite, "1").is_empty());
+    for i in 0..traders {
+        assert_eq!(suite.nft_owner(&format!("t{i}")), offerer(i));
+    }
+}
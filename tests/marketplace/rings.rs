@@ -0,0 +1,130 @@
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+
+use gecko_party_marketplace::contract::{
+    MAX_RING_PARTICIPANTS, MIN_RING_PARTICIPANTS, RING_DEPOSIT_PERIOD,
+};
+use gecko_party_marketplace::msg::{ExecuteMsg, QueryMsg, ReceiveNftMsg, RingParticipant};
+use gecko_party_marketplace::state::Ring;
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, attribute, offerer, Suite, BUYER, SELLER, TRADER};
+
+// each participant gives their NFT to the next one, the last to the first
+const RING: [(&str, &str); 3] = [(SELLER, "1"), (BUYER, "2"), (TRADER, "3")];
+
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    for (owner, id) in RING {
+        suite.mint(owner, id);
+    }
+    suite
+}
+
+fn propose(suite: &mut Suite, sender: &str, legs: &[(&str, &str)]) -> AnyResult<AppResponse> {
+    let participants = legs
+        .iter()
+        .map(|(address, id)| RingParticipant {
+            address: address.to_string(),
+            nft: suite.nft_ref(id),
+        })
+        .collect();
+    suite.execute(sender, &ExecuteMsg::ProposeRing { participants }, &[])
+}
+
+fn deposit(suite: &mut Suite, owner: &str, token_id: &str) -> AnyResult<AppResponse> {
+    suite.send_nft(owner, token_id, &ReceiveNftMsg::DepositRing { id: 1 })
+}
+
+fn invalid_ring() -> ContractError {
+    ContractError::InvalidRing {
+        min: MIN_RING_PARTICIPANTS as u32,
+        max: MAX_RING_PARTICIPANTS as u32,
+    }
+}
+
+#[test]
+fn the_last_deposit_settles_the_ring() {
+    let mut suite = setup();
+    propose(&mut suite, SELLER, &RING).unwrap();
+
+    deposit(&mut suite, SELLER, "1").unwrap();
+    suite.mint(BUYER, "x");
+    assert_error(
+        deposit(&mut suite, BUYER, "x"),
+        ContractError::UnexpectedNft {},
+    );
+    let res = deposit(&mut suite, BUYER, "2").unwrap();
+    assert_eq!(attribute(&res, "settled"), None);
+    let ring: Ring = suite.query(&QueryMsg::GetRing { id: 1 });
+    assert_eq!(ring.legs.iter().filter(|leg| leg.nft.deposited).count(), 2);
+
+    let res = deposit(&mut suite, TRADER, "3").unwrap();
+    assert_eq!(attribute(&res, "settled"), Some("true".to_string()));
+    assert_eq!(suite.nft_owner("1"), BUYER);
+    assert_eq!(suite.nft_owner("2"), TRADER);
+    assert_eq!(suite.nft_owner("3"), SELLER);
+}
+
+#[test]
+fn participants_withdraw_and_anyone_refunds_late_rings() {
+    let mut suite = setup();
+    propose(&mut suite, SELLER, &RING).unwrap();
+    deposit(&mut suite, SELLER, "1").unwrap();
+
+    let withdraw = ExecuteMsg::WithdrawRing { id: 1 };
+    assert_error(
+        suite.execute(&offerer(0), &withdraw, &[]),
+        ContractError::Unauthorized {},
+    );
+    suite.execute(BUYER, &withdraw, &[]).unwrap();
+    assert_eq!(suite.nft_owner("1"), SELLER);
+
+    propose(&mut suite, SELLER, &RING).unwrap();
+    let refund = ExecuteMsg::RefundRing { id: 2 };
+    suite
+        .send_nft(TRADER, "3", &ReceiveNftMsg::DepositRing { id: 2 })
+        .unwrap();
+    assert_error(
+        suite.execute(&offerer(0), &refund, &[]),
+        ContractError::RingNotRefundable {},
+    );
+
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(RING_DEPOSIT_PERIOD + 1));
+    assert_error(
+        suite.send_nft(SELLER, "1", &ReceiveNftMsg::DepositRing { id: 2 }),
+        ContractError::TradeExpired {},
+    );
+    suite.execute(&offerer(0), &refund, &[]).unwrap();
+    assert_eq!(suite.nft_owner("3"), TRADER);
+}
+
+#[test]
+fn rings_need_three_distinct_participants_including_the_proposer() {
+    let mut suite = setup();
+    assert_error(propose(&mut suite, SELLER, &RING[..1]), invalid_ring());
+    // two participants trade directly
+    assert_error(propose(&mut suite, SELLER, &RING[..2]), invalid_ring());
+    assert_error(
+        propose(
+            &mut suite,
+            SELLER,
+            &[(SELLER, "1"), (SELLER, "2"), (TRADER, "3")],
+        ),
+        invalid_ring(),
+    );
+    assert_error(
+        propose(
+            &mut suite,
+            SELLER,
+            &[(SELLER, "1"), (BUYER, "1"), (TRADER, "3")],
+        ),
+        ContractError::DuplicateNft {},
+    );
+    assert_error(
+        propose(&mut suite, &offerer(0), &RING),
+        ContractError::Unauthorized {},
+    );
+}
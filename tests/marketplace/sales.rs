@@ -0,0 +1,106 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{ExecuteMsg, PageResponse, QueryMsg};
+use gecko_party_marketplace::state::{CoinType, Sale, SaleKind};
+
+use crate::suite::{offerer, Suite, BUYER, SELLER};
+
+// 1: "1" bought by BUYER, 2: "1" sold on by BUYER through an offer,
+// 3: "2" bought by BUYER, 4: "3" bought back by SELLER itself
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    for id in ["1", "2", "3"] {
+        suite.mint(SELLER, id);
+    }
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.buy(BUYER, "1", 100).unwrap();
+
+    suite.list(BUYER, "1", 200, None).unwrap();
+    suite.offer(&offerer(0), "1", 150).unwrap();
+    suite
+        .execute(
+            BUYER,
+            &ExecuteMsg::AcceptOffer {
+                id: "1".to_string(),
+                offerer: offerer(0),
+            },
+            &[],
+        )
+        .unwrap();
+
+    suite.list(SELLER, "2", 80, None).unwrap();
+    suite.buy(BUYER, "2", 80).unwrap();
+    suite.list(SELLER, "3", 60, None).unwrap();
+    suite.buy(SELLER, "3", 60).unwrap();
+    suite
+}
+
+fn ids(page: &PageResponse<Sale, u64>) -> Vec<u64> {
+    page.items.iter().map(|sale| sale.id).collect()
+}
+
+fn by_address(suite: &Suite, address: &str, start_after: Option<u64>) -> PageResponse<Sale, u64> {
+    suite.query(&QueryMsg::GetSalesByAddress {
+        address: address.to_string(),
+        start_after,
+        limit: Some(2),
+    })
+}
+
+#[test]
+fn sales_are_recorded_newest_first() {
+    let suite = setup();
+    let all: PageResponse<Sale, u64> = suite.query(&QueryMsg::GetAllSales {
+        start_after: None,
+        limit: None,
+    });
+    assert_eq!(ids(&all), vec![4, 3, 2, 1]);
+
+    let sale = &all.items[2];
+    assert_eq!(sale.token_id, "1");
+    assert_eq!(sale.seller, BUYER);
+    assert_eq!(sale.buyer, offerer(0));
+    assert_eq!(sale.price, Uint256::from(150u128));
+    assert_eq!(sale.currency, Some(CoinType::Native));
+    assert_eq!(sale.kind, SaleKind::Offer);
+    assert_eq!(all.items[3].kind, SaleKind::BuyNow);
+
+    let page: PageResponse<Sale, u64> = suite.query(&QueryMsg::GetAllSales {
+        start_after: Some(3),
+        limit: Some(1),
+    });
+    assert_eq!(ids(&page), vec![2]);
+    assert_eq!(page.next_start_after, Some(2));
+
+    let token: PageResponse<Sale, u64> = suite.query(&QueryMsg::GetSalesByToken {
+        id: "1".to_string(),
+        start_after: None,
+        limit: None,
+    });
+    assert_eq!(ids(&token), vec![2, 1]);
+}
+
+#[test]
+fn an_address_sees_what_it_sold_and_bought() {
+    let suite = setup();
+
+    // bought, sold, bought
+    let page = by_address(&suite, BUYER, None);
+    assert_eq!(ids(&page), vec![3, 2]);
+    assert_eq!(page.next_start_after, Some(2));
+    let page = by_address(&suite, BUYER, page.next_start_after);
+    assert_eq!(ids(&page), vec![1]);
+    assert_eq!(page.next_start_after, None);
+
+    // its own purchase shows up once
+    let page = by_address(&suite, SELLER, None);
+    assert_eq!(ids(&page), vec![4, 3]);
+    let page = by_address(&suite, SELLER, page.next_start_after);
+    assert_eq!(ids(&page), vec![1]);
+
+    // a cursor that is not one of the address's sales
+    let page = by_address(&suite, &offerer(0), Some(3));
+    assert_eq!(ids(&page), vec![2]);
+    let page = by_address(&suite, &offerer(0), Some(2));
+    assert!(page.items.is_empty());
+}
@@ -0,0 +1,226 @@
+use cosmwasm_std::{Addr, Uint256};
+use cw721::Cw721ExecuteMsg;
+use cw_multi_test::Executor;
+use cw_utils::Expiration;
+
+use gecko_party_marketplace::msg::{
+    AssetAmount, ExecuteMsg, FailureReason, NftTransfer, QueryMsg, ReceiveNftMsg, Settlement,
+    SimulationResponse,
+};
+use gecko_party_marketplace::state::{AcceptancePolicy, CoinType};
+
+use crate::suite::{offerer, Suite, BUYER, SELLER, TRADER};
+
+fn paid(amount: u128, asset: CoinType) -> AssetAmount {
+    AssetAmount {
+        asset,
+        amount: Uint256::from(amount),
+    }
+}
+
+fn simulate_buy(suite: &Suite, id: &str, funds: AssetAmount) -> SimulationResponse {
+    suite.query(&QueryMsg::SimulateBuy {
+        id: id.to_string(),
+        buyer: BUYER.to_string(),
+        funds,
+    })
+}
+
+fn simulate_offer(suite: &Suite, offerer: &str, seller: &str) -> SimulationResponse {
+    suite.query(&QueryMsg::SimulateAcceptOffer {
+        id: "1".to_string(),
+        offerer: offerer.to_string(),
+        seller: seller.to_string(),
+    })
+}
+
+fn simulate_trade(suite: &Suite, id: &str, trader: &str) -> SimulationResponse {
+    suite.query(&QueryMsg::SimulateAcceptTrade {
+        id: id.to_string(),
+        trader: trader.to_string(),
+        seller: SELLER.to_string(),
+    })
+}
+
+fn failed(reason: FailureReason) -> SimulationResponse {
+    SimulationResponse::Failure { reason }
+}
+
+fn transfer(suite: &Suite, token_id: &str, recipient: &str) -> NftTransfer {
+    NftTransfer {
+        collection: suite.nft.to_string(),
+        token_id: token_id.to_string(),
+        recipient: recipient.to_string(),
+    }
+}
+
+#[test]
+fn buying_checks_the_listing_and_payment() {
+    let mut suite = Suite::new();
+    suite.mint(SELLER, "1");
+    suite.mint(SELLER, "2");
+    suite
+        .list(SELLER, "1", 100, Some(CoinType::Native))
+        .unwrap();
+    suite
+        .send_nft(
+            SELLER,
+            "2",
+            &ReceiveNftMsg::NewListing {
+                price: Uint256::from(100u128),
+                policy: AcceptancePolicy::Flexible {
+                    buy_now: false,
+                    offers: true,
+                    min_offer: None,
+                    trades: true,
+                },
+                preferences: None,
+                auto_accept_at: None,
+                currency: None,
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        simulate_buy(&suite, "1", paid(100, CoinType::Native)),
+        SimulationResponse::Success {
+            settlement: Settlement {
+                proceeds: Some(paid(100, CoinType::Native)),
+                paid_to_wallet: false,
+                nft_transfers: vec![transfer(&suite, "1", BUYER)],
+            }
+        }
+    );
+    assert_eq!(
+        simulate_buy(&suite, "1", paid(90, CoinType::Native)),
+        failed(FailureReason::IncorrectPayment {
+            price: Uint256::from(100u128)
+        })
+    );
+    assert_eq!(
+        simulate_buy(&suite, "1", paid(100, CoinType::Cw20)),
+        failed(FailureReason::CurrencyNotAccepted {})
+    );
+    assert_eq!(
+        simulate_buy(&suite, "2", paid(100, CoinType::Native)),
+        failed(FailureReason::BuyNowDisabled {})
+    );
+    assert_eq!(
+        simulate_buy(&suite, "9", paid(100, CoinType::Native)),
+        failed(FailureReason::NotListed {})
+    );
+
+    // the simulation holds up when the buy is sent
+    suite.buy(BUYER, "1", 100).unwrap();
+    assert_eq!(
+        simulate_buy(&suite, "1", paid(100, CoinType::Native)),
+        failed(FailureReason::NotListed {})
+    );
+}
+
+#[test]
+fn proceeds_go_to_wallets_when_pushed() {
+    let mut suite = Suite::with_config(|msg| msg.push_to_wallets = Some(true));
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.offer(&offerer(0), "1", 40).unwrap();
+
+    let SimulationResponse::Success { settlement } =
+        simulate_buy(&suite, "1", paid(100, CoinType::Cw20))
+    else {
+        panic!("a listing taking any currency can be bought in cw20");
+    };
+    assert!(settlement.paid_to_wallet);
+
+    assert_eq!(
+        simulate_offer(&suite, &offerer(0), SELLER),
+        SimulationResponse::Success {
+            settlement: Settlement {
+                proceeds: Some(paid(40, CoinType::Native)),
+                paid_to_wallet: true,
+                nft_transfers: vec![transfer(&suite, "1", &offerer(0))],
+            }
+        }
+    );
+    assert_eq!(
+        simulate_offer(&suite, &offerer(0), BUYER),
+        failed(FailureReason::Unauthorized {})
+    );
+    assert_eq!(
+        simulate_offer(&suite, &offerer(1), SELLER),
+        failed(FailureReason::OfferNotFound {})
+    );
+}
+
+#[test]
+fn trades_and_swaps_check_expiry_and_deposits() {
+    let mut suite = Suite::new();
+    for id in ["1", "2"] {
+        suite.mint(SELLER, id);
+        suite.list(SELLER, id, 100, None).unwrap();
+    }
+    suite.mint(TRADER, "a");
+    suite.mint(offerer(0).as_str(), "b");
+    suite.mint(offerer(1).as_str(), "c");
+    let height = suite.app.block_info().height;
+
+    suite.trade(TRADER, "a", "1").unwrap();
+    suite
+        .send_nft(
+            &offerer(0),
+            "b",
+            &ReceiveNftMsg::NewTrade {
+                target: "1".to_string(),
+                expires: Some(Expiration::AtHeight(height + 1)),
+            },
+        )
+        .unwrap();
+    suite
+        .execute(
+            &offerer(1),
+            &ExecuteMsg::ProposeSwap {
+                asked_ids: vec!["2".to_string()],
+                offered: vec![suite.nft_ref("c")],
+            },
+            &[],
+        )
+        .unwrap();
+    suite.app.update_block(|block| block.height += 1);
+
+    assert_eq!(
+        simulate_trade(&suite, "1", TRADER),
+        SimulationResponse::Success {
+            settlement: Settlement {
+                proceeds: None,
+                paid_to_wallet: false,
+                nft_transfers: vec![transfer(&suite, "1", TRADER), transfer(&suite, "a", SELLER),],
+            }
+        }
+    );
+    assert_eq!(
+        simulate_trade(&suite, "1", &offerer(0)),
+        failed(FailureReason::TradeExpired {})
+    );
+    assert_eq!(
+        simulate_trade(&suite, "1", BUYER),
+        failed(FailureReason::TradeNotFound {})
+    );
+
+    // "c" left its owner without being deposited, so the swap can't settle
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(offerer(1)),
+            suite.nft.clone(),
+            &Cw721ExecuteMsg::TransferNft {
+                recipient: BUYER.to_string(),
+                token_id: "c".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        simulate_trade(&suite, "2", &offerer(1)),
+        failed(FailureReason::DepositsIncomplete {})
+    );
+}
@@ -0,0 +1,95 @@
+use cosmwasm_std::Uint256;
+
+use gecko_party_marketplace::msg::{ExecuteMsg, ReceiveMsg};
+use gecko_party_marketplace::state::CoinType;
+
+use crate::suite::{assert_covered, offerer, Suite, BUYER, FUNDS, SELLER};
+
+#[test]
+fn native_funds_stay_covered() {
+    let mut suite = Suite::new();
+    let (alice, bob) = (offerer(0), offerer(1));
+    suite.mint(SELLER, "1");
+    suite.mint(SELLER, "2");
+    suite.list(SELLER, "1", 100, None).unwrap();
+    suite.list(SELLER, "2", 100, None).unwrap();
+
+    suite.offer(&alice, "1", 40).unwrap();
+    assert_covered(&suite.solvency(CoinType::Native), 40, 0);
+
+    // cancelled offers are credited back, not sent
+    suite
+        .execute(
+            &alice,
+            &ExecuteMsg::CancelOffer {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_covered(&suite.solvency(CoinType::Native), 0, 40);
+
+    // selling "2" refunds bob and credits the seller
+    suite.offer(&bob, "2", 30).unwrap();
+    suite.buy(BUYER, "2", 100).unwrap();
+    assert_covered(&suite.solvency(CoinType::Native), 0, 170);
+
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::Withdraw {
+                asset: CoinType::Native,
+                amount: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert_covered(&suite.solvency(CoinType::Native), 0, 70);
+    assert_covered(&suite.solvency(CoinType::Cw20), 0, 0);
+}
+
+#[test]
+fn cw20_funds_stay_covered() {
+    let mut suite = Suite::new();
+    let alice = offerer(0);
+    suite.mint(SELLER, "1");
+    suite.list(SELLER, "1", 100, Some(CoinType::Cw20)).unwrap();
+
+    suite
+        .send_tokens(
+            &alice,
+            60,
+            &ReceiveMsg::Offer {
+                target: "1".to_string(),
+                offered_price: Uint256::from(60u128),
+            },
+        )
+        .unwrap();
+    assert_covered(&suite.solvency(CoinType::Cw20), 60, 0);
+
+    suite
+        .send_tokens(
+            BUYER,
+            100,
+            &ReceiveMsg::Buy {
+                id: "1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(suite.nft_owner("1"), BUYER);
+    assert_covered(&suite.solvency(CoinType::Cw20), 0, 160);
+
+    suite
+        .execute(
+            &alice,
+            &ExecuteMsg::Withdraw {
+                asset: CoinType::Cw20,
+                amount: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.token_balance(&alice), FUNDS);
+    assert_covered(&suite.solvency(CoinType::Cw20), 0, 100);
+    assert_covered(&suite.solvency(CoinType::Native), 0, 0);
+}
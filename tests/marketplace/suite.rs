@@ -0,0 +1,539 @@
+use cosmwasm_std::{
+    coins, to_json_binary, Addr, Binary, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Response,
+    StdError, StdResult, Uint128, Uint256, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+use cw721::{Cw721QueryMsg, OwnerOfResponse};
+use cw721_base::Cw721Contract;
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+use cw_storage_plus::Map;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use gecko_party_marketplace::contract::{self, NATIVE_DENOM};
+use gecko_party_marketplace::msg::{
+    ExecuteMsg, InstantiateMsg, NftMetadata, NftRef, NftTrait, QueryMsg, ReceiveMsg, ReceiveNftMsg,
+    SolvencyResponse,
+};
+use gecko_party_marketplace::state::{AcceptancePolicy, CoinType};
+use gecko_party_marketplace::ContractError;
+
+pub const ADMIN: &str = "admin";
+pub const SELLER: &str = "seller";
+pub const BUYER: &str = "buyer";
+pub const TRADER: &str = "trader";
+// native funds every test address starts with
+pub const FUNDS: u128 = 1_000_000;
+
+pub fn offerer(i: usize) -> String {
+    format!("offerer{i}")
+}
+
+pub fn native(amount: u128) -> Vec<Coin> {
+    coins(amount, NATIVE_DENOM)
+}
+
+// buy now, offers and trades, with no offer floor
+pub fn flexible() -> AcceptancePolicy {
+    AcceptancePolicy::Flexible {
+        buy_now: true,
+        offers: true,
+        min_offer: None,
+        trades: true,
+    }
+}
+
+fn market_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(
+        ContractWrapper::new(contract::execute, contract::instantiate, contract::query)
+            .with_reply(contract::reply)
+            .with_migrate(contract::migrate),
+    )
+}
+
+// cw721-base storing `T` as the token extension
+fn nft_contract<T>() -> Box<dyn Contract<Empty>>
+where
+    T: Serialize + DeserializeOwned + Clone + std::fmt::Debug + 'static,
+{
+    Box::new(ContractWrapper::new(
+        |deps, env, info, msg: cw721_base::ExecuteMsg<T, Empty>| {
+            Cw721Contract::<T, Empty, Empty, Empty>::default().execute(deps, env, info, msg)
+        },
+        |deps, env, info, msg: cw721_base::InstantiateMsg| {
+            Cw721Contract::<T, Empty, Empty, Empty>::default().instantiate(deps, env, info, msg)
+        },
+        |deps, env, msg: cw721_base::QueryMsg<Empty>| {
+            Cw721Contract::<T, Empty, Empty, Empty>::default().query(deps, env, msg)
+        },
+    ))
+}
+
+// a collection outside the usual layout: numeric trait values and no other fields
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LizardMetadata {
+    pub attributes: Vec<LizardTrait>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LizardTrait {
+    pub trait_type: String,
+    pub value: u64,
+}
+
+pub fn traits(pairs: &[(&str, &str)]) -> NftMetadata {
+    NftMetadata {
+        image: None,
+        image_data: None,
+        external_url: None,
+        description: None,
+        name: None,
+        attributes: Some(
+            pairs
+                .iter()
+                .map(|(trait_type, value)| NftTrait {
+                    display_type: None,
+                    trait_type: trait_type.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+        ),
+        background_color: None,
+        animation_url: None,
+        youtube_url: None,
+    }
+}
+
+// a bare cw20: balances set at instantiate, transfers and sends, balance queries
+const TOKEN_BALANCES: Map<&Addr, Uint128> = Map::new("balances");
+
+fn token_instantiate(
+    deps: DepsMut,
+    _: Env,
+    _: MessageInfo,
+    balances: Vec<(String, Uint128)>,
+) -> StdResult<Response> {
+    for (address, amount) in balances {
+        TOKEN_BALANCES.save(deps.storage, &Addr::unchecked(address), &amount)?;
+    }
+    Ok(Response::new())
+}
+
+fn token_move(deps: DepsMut, from: &Addr, to: &Addr, amount: Uint128) -> StdResult<()> {
+    TOKEN_BALANCES.update(deps.storage, from, |balance| {
+        balance
+            .unwrap_or_default()
+            .checked_sub(amount)
+            .map_err(StdError::overflow)
+    })?;
+    TOKEN_BALANCES.update(deps.storage, to, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default() + amount)
+    })?;
+    Ok(())
+}
+
+fn token_execute(
+    deps: DepsMut,
+    _: Env,
+    info: MessageInfo,
+    msg: Cw20ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        Cw20ExecuteMsg::Transfer { recipient, amount } => {
+            token_move(deps, &info.sender, &Addr::unchecked(recipient), amount)?;
+            Ok(Response::new())
+        }
+        Cw20ExecuteMsg::Send {
+            contract,
+            amount,
+            msg,
+        } => {
+            token_move(deps, &info.sender, &Addr::unchecked(&contract), amount)?;
+            Ok(Response::new().add_message(WasmMsg::Execute {
+                contract_addr: contract,
+                msg: to_json_binary(&ExecuteMsg::Receive(Cw20ReceiveMsg {
+                    sender: info.sender.to_string(),
+                    amount,
+                    msg,
+                }))?,
+                funds: vec![],
+            }))
+        }
+        _ => Err(StdError::generic_err("not supported")),
+    }
+}
+
+fn token_query(deps: Deps, _: Env, msg: Cw20QueryMsg) -> StdResult<Binary> {
+    match msg {
+        Cw20QueryMsg::Balance { address } => to_json_binary(&BalanceResponse {
+            balance: TOKEN_BALANCES
+                .may_load(deps.storage, &Addr::unchecked(address))?
+                .unwrap_or_default(),
+        }),
+        _ => Err(StdError::generic_err("not supported")),
+    }
+}
+
+fn token_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        token_execute,
+        token_instantiate,
+        token_query,
+    ))
+}
+
+fn instantiate_collection(app: &mut App, code_id: u64, name: &str) -> Addr {
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(ADMIN),
+        &cw721_base::InstantiateMsg {
+            name: name.to_string(),
+            symbol: name.to_uppercase(),
+            minter: ADMIN.to_string(),
+        },
+        &[],
+        name,
+        None,
+    )
+    .unwrap()
+}
+
+/// A marketplace for a fresh cw721 collection plus a second whitelisted one,
+/// with native and cw20 funds for the named addresses.
+pub struct Suite {
+    pub app: App,
+    pub market: Addr,
+    pub nft: Addr,
+    pub lizards: Addr,
+    pub token: Addr,
+    nft_code: u64,
+}
+
+impl Suite {
+    pub fn new() -> Self {
+        Self::with_config(|_| {})
+    }
+
+    /// Lets a test adjust the marketplace instantiate message before it is sent.
+    pub fn with_config(configure: impl FnOnce(&mut InstantiateMsg)) -> Self {
+        let mut funded = vec![SELLER.to_string(), BUYER.to_string(), TRADER.to_string()];
+        funded.extend((0..40).map(offerer));
+        let mut app = App::new(|router, _, storage| {
+            for address in &funded {
+                router
+                    .bank
+                    .init_balance(storage, &Addr::unchecked(address), native(FUNDS))
+                    .unwrap();
+            }
+        });
+        let nft_code = app.store_code(nft_contract::<Option<NftMetadata>>());
+        let lizard_code = app.store_code(nft_contract::<Option<LizardMetadata>>());
+        let market_code = app.store_code(market_contract());
+        let token_code = app.store_code(token_contract());
+
+        let token = app
+            .instantiate_contract(
+                token_code,
+                Addr::unchecked(ADMIN),
+                &funded
+                    .iter()
+                    .map(|address| (address.clone(), Uint128::from(FUNDS)))
+                    .collect::<Vec<_>>(),
+                &[],
+                "token",
+                None,
+            )
+            .unwrap();
+
+        let nft = instantiate_collection(&mut app, nft_code, "geckos");
+        let lizards = instantiate_collection(&mut app, lizard_code, "lizards");
+        let mut msg = InstantiateMsg {
+            cw721_address: nft.to_string(),
+            cw20_address: token.to_string(),
+            push_to_wallets: None,
+            collections: Some(vec![lizards.to_string()]),
+        };
+        configure(&mut msg);
+        let market = app
+            .instantiate_contract(
+                market_code,
+                Addr::unchecked(ADMIN),
+                &msg,
+                &[],
+                "marketplace",
+                Some(ADMIN.to_string()),
+            )
+            .unwrap();
+
+        Suite {
+            app,
+            market,
+            nft,
+            lizards,
+            token,
+            nft_code,
+        }
+    }
+
+    // a collection the marketplace does not know about
+    pub fn new_collection(&mut self, name: &str) -> Addr {
+        instantiate_collection(&mut self.app, self.nft_code, name)
+    }
+
+    pub fn mint(&mut self, owner: &str, token_id: &str) {
+        self.mint_in(&self.nft.clone(), owner, token_id);
+    }
+
+    pub fn mint_in(&mut self, collection: &Addr, owner: &str, token_id: &str) {
+        self.mint_with(collection, owner, token_id, None::<NftMetadata>);
+    }
+
+    pub fn mint_with<T: Serialize + std::fmt::Debug>(
+        &mut self,
+        collection: &Addr,
+        owner: &str,
+        token_id: &str,
+        extension: T,
+    ) {
+        self.app
+            .execute_contract(
+                Addr::unchecked(ADMIN),
+                collection.clone(),
+                &cw721_base::ExecuteMsg::<T, Empty>::Mint {
+                    token_id: token_id.to_string(),
+                    owner: owner.to_string(),
+                    token_uri: None,
+                    extension,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    pub fn nft_ref(&self, token_id: &str) -> NftRef {
+        nft_ref(&self.nft, token_id)
+    }
+
+    // lets the marketplace move the NFT without escrowing it
+    pub fn approve(&mut self, owner: &str, token_id: &str) {
+        self.app
+            .execute_contract(
+                Addr::unchecked(owner),
+                self.nft.clone(),
+                &cw721_base::ExecuteMsg::<cw721_base::Extension, Empty>::Approve {
+                    spender: self.market.to_string(),
+                    token_id: token_id.to_string(),
+                    expires: None,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    pub fn send_nft(
+        &mut self,
+        owner: &str,
+        token_id: &str,
+        msg: &ReceiveNftMsg,
+    ) -> AnyResult<AppResponse> {
+        self.send_nft_in(&self.nft.clone(), owner, token_id, msg)
+    }
+
+    pub fn send_nft_in(
+        &mut self,
+        collection: &Addr,
+        owner: &str,
+        token_id: &str,
+        msg: &ReceiveNftMsg,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(owner),
+            collection.clone(),
+            &cw721_base::ExecuteMsg::<cw721_base::Extension, Empty>::SendNft {
+                contract: self.market.to_string(),
+                token_id: token_id.to_string(),
+                msg: to_json_binary(msg).unwrap(),
+            },
+            &[],
+        )
+    }
+
+    pub fn list(
+        &mut self,
+        owner: &str,
+        token_id: &str,
+        price: u128,
+        currency: Option<CoinType>,
+    ) -> AnyResult<AppResponse> {
+        self.send_nft(
+            owner,
+            token_id,
+            &ReceiveNftMsg::NewListing {
+                price: Uint256::from(price),
+                policy: flexible(),
+                preferences: None,
+                auto_accept_at: None,
+                currency,
+            },
+        )
+    }
+
+    pub fn trade(
+        &mut self,
+        trader: &str,
+        offered_id: &str,
+        asked_id: &str,
+    ) -> AnyResult<AppResponse> {
+        self.send_nft(
+            trader,
+            offered_id,
+            &ReceiveNftMsg::NewTrade {
+                target: asked_id.to_string(),
+                expires: None,
+            },
+        )
+    }
+
+    pub fn execute(
+        &mut self,
+        sender: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), self.market.clone(), msg, funds)
+    }
+
+    pub fn offer(&mut self, offerer: &str, token_id: &str, amount: u128) -> AnyResult<AppResponse> {
+        self.execute(
+            offerer,
+            &ExecuteMsg::Offer {
+                target: token_id.to_string(),
+                offered_price: Uint256::from(amount),
+            },
+            &native(amount),
+        )
+    }
+
+    pub fn buy(&mut self, buyer: &str, token_id: &str, price: u128) -> AnyResult<AppResponse> {
+        self.execute(
+            buyer,
+            &ExecuteMsg::Buy {
+                id: token_id.to_string(),
+            },
+            &native(price),
+        )
+    }
+
+    // pays the marketplace in the cw20 token
+    pub fn send_tokens(
+        &mut self,
+        sender: &str,
+        amount: u128,
+        msg: &ReceiveMsg,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            self.token.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: self.market.to_string(),
+                amount: Uint128::from(amount),
+                msg: to_json_binary(msg).unwrap(),
+            },
+            &[],
+        )
+    }
+
+    pub fn token_balance(&self, address: &str) -> u128 {
+        let res: BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                self.token.clone(),
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+
+    pub fn solvency(&self, asset: CoinType) -> SolvencyResponse {
+        let all: Vec<SolvencyResponse> = self.query(&QueryMsg::Solvency {});
+        all.into_iter().find(|s| s.asset == asset).unwrap()
+    }
+
+    pub fn query<T: DeserializeOwned>(&self, msg: &QueryMsg) -> T {
+        self.app
+            .wrap()
+            .query_wasm_smart(self.market.clone(), msg)
+            .unwrap()
+    }
+
+    pub fn nft_owner(&self, token_id: &str) -> String {
+        self.nft_owner_in(&self.nft, token_id)
+    }
+
+    pub fn nft_owner_in(&self, collection: &Addr, token_id: &str) -> String {
+        let owner: OwnerOfResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                collection.clone(),
+                &Cw721QueryMsg::OwnerOf {
+                    token_id: token_id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        owner.owner
+    }
+
+    // native funds held by the address itself
+    pub fn bank(&self, address: &str) -> u128 {
+        self.app
+            .wrap()
+            .query_balance(address, NATIVE_DENOM)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    // credited to the address in the marketplace, in the native denom
+    pub fn balance(&self, address: &str) -> Uint256 {
+        self.query(&QueryMsg::Balance {
+            address: address.to_string(),
+            asset: CoinType::Native,
+        })
+    }
+}
+
+pub fn nft_ref(collection: &Addr, token_id: &str) -> NftRef {
+    NftRef {
+        collection: collection.to_string(),
+        token_id: token_id.to_string(),
+    }
+}
+
+// first value of a response attribute, across all events
+pub fn attribute(res: &AppResponse, key: &str) -> Option<String> {
+    res.events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.clone())
+}
+
+// everything held is accounted for, and nothing more
+pub fn assert_covered(solvency: &SolvencyResponse, escrowed: u128, credited: u128) {
+    assert_eq!(solvency.escrowed, Uint256::from(escrowed));
+    assert_eq!(solvency.credited, Uint256::from(credited));
+    assert_eq!(solvency.balance, Uint256::from(escrowed + credited));
+    assert!(solvency.covered);
+}
+
+pub fn assert_error<T: std::fmt::Debug>(res: AnyResult<T>, expected: ContractError) {
+    let err = res.unwrap_err();
+    assert_eq!(err.root_cause().to_string(), expected.to_string());
+}
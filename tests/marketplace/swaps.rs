@@ -0,0 +1,125 @@
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+
+use gecko_party_marketplace::contract::{MAX_SWAP_NFTS, SWAP_DEPOSIT_PERIOD};
+use gecko_party_marketplace::msg::{ExecuteMsg, ReceiveNftMsg};
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{assert_error, Suite, BUYER, SELLER, TRADER};
+
+fn setup() -> Suite {
+    let mut suite = Suite::new();
+    for id in ["1", "2"] {
+        suite.mint(SELLER, id);
+        suite.list(SELLER, id, 100, None).unwrap();
+    }
+    for id in ["a", "b", "c"] {
+        suite.mint(TRADER, id);
+    }
+    suite
+}
+
+fn propose(suite: &mut Suite, asked: &[&str], offered: &[&str]) -> AnyResult<AppResponse> {
+    let offered = offered.iter().map(|id| suite.nft_ref(id)).collect();
+    suite.execute(
+        TRADER,
+        &ExecuteMsg::ProposeSwap {
+            asked_ids: asked.iter().map(|id| id.to_string()).collect(),
+            offered,
+        },
+        &[],
+    )
+}
+
+fn deposit(suite: &mut Suite, token_id: &str) -> AnyResult<AppResponse> {
+    suite.send_nft(
+        TRADER,
+        token_id,
+        &ReceiveNftMsg::DepositSwap {
+            target: "1".to_string(),
+        },
+    )
+}
+
+fn refund(suite: &mut Suite, sender: &str) -> AnyResult<AppResponse> {
+    suite.execute(
+        sender,
+        &ExecuteMsg::RefundSwap {
+            id: "1".to_string(),
+            trader: TRADER.to_string(),
+        },
+        &[],
+    )
+}
+
+fn expire(suite: &mut Suite) {
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(SWAP_DEPOSIT_PERIOD + 1));
+}
+
+#[test]
+fn accepting_moves_deposited_and_approved_nfts() {
+    let mut suite = setup();
+    assert_error(
+        propose(&mut suite, &["1", "2"], &[]),
+        ContractError::InvalidNftCount {
+            max: MAX_SWAP_NFTS as u32,
+        },
+    );
+    propose(&mut suite, &["1", "2"], &["a", "b"]).unwrap();
+    deposit(&mut suite, "a").unwrap();
+    assert_error(deposit(&mut suite, "c"), ContractError::UnexpectedNft {});
+
+    let accept = ExecuteMsg::AcceptTrade {
+        id: "1".to_string(),
+        trader: TRADER.to_string(),
+    };
+    // "b" is neither deposited nor approved yet
+    assert!(suite.execute(SELLER, &accept, &[]).is_err());
+    suite.approve(TRADER, "b");
+    assert_error(
+        suite.execute(BUYER, &accept, &[]),
+        ContractError::Unauthorized {},
+    );
+
+    suite.execute(SELLER, &accept, &[]).unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner("2"), TRADER);
+    assert_eq!(suite.nft_owner("a"), SELLER);
+    assert_eq!(suite.nft_owner("b"), SELLER);
+}
+
+#[test]
+fn refunds_follow_the_deadline() {
+    let mut suite = setup();
+
+    // partly deposited: anyone can refund once the deadline passed
+    propose(&mut suite, &["1", "2"], &["a", "b"]).unwrap();
+    deposit(&mut suite, "a").unwrap();
+    assert_error(
+        refund(&mut suite, BUYER),
+        ContractError::SwapNotRefundable {},
+    );
+    expire(&mut suite);
+    refund(&mut suite, BUYER).unwrap();
+    assert_eq!(suite.nft_owner("a"), TRADER);
+
+    // nothing deposited: only the trader
+    propose(&mut suite, &["1"], &["b"]).unwrap();
+    expire(&mut suite);
+    assert_error(refund(&mut suite, BUYER), ContractError::Unauthorized {});
+    refund(&mut suite, TRADER).unwrap();
+}
+
+#[test]
+fn trades_and_swaps_never_overlap() {
+    let mut suite = setup();
+    suite.trade(TRADER, "a", "1").unwrap();
+    assert_error(
+        propose(&mut suite, &["1", "2"], &["b"]),
+        ContractError::TradeExists {},
+    );
+    propose(&mut suite, &["2"], &["b"]).unwrap();
+    assert_error(suite.trade(TRADER, "c", "2"), ContractError::TradeExists {});
+}
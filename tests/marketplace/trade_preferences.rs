@@ -0,0 +1,213 @@
+use cosmwasm_std::{Addr, Uint256};
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::AppResponse;
+
+use gecko_party_marketplace::msg::{ExecuteMsg, ReceiveNftMsg, TradePreferencesMsg};
+use gecko_party_marketplace::state::TraitFilter;
+use gecko_party_marketplace::ContractError;
+
+use crate::suite::{
+    assert_error, flexible, nft_ref, traits, LizardMetadata, LizardTrait, Suite, SELLER, TRADER,
+};
+
+fn preferences() -> TradePreferencesMsg {
+    TradePreferencesMsg {
+        token_ids: vec![],
+        collection: None,
+        traits: vec![],
+        auto_accept: false,
+    }
+}
+
+fn filter(trait_type: &str, value: &str) -> TraitFilter {
+    TraitFilter {
+        trait_type: trait_type.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn list_wanting(suite: &mut Suite, preferences: TradePreferencesMsg) {
+    suite.mint(SELLER, "1");
+    suite
+        .send_nft(
+            SELLER,
+            "1",
+            &ReceiveNftMsg::NewListing {
+                price: Uint256::from(100u128),
+                policy: flexible(),
+                preferences: Some(preferences),
+                auto_accept_at: None,
+                currency: None,
+            },
+        )
+        .unwrap();
+}
+
+fn trade_in(suite: &mut Suite, collection: &Addr, offered_id: &str) -> AnyResult<AppResponse> {
+    suite.send_nft_in(
+        collection,
+        TRADER,
+        offered_id,
+        &ReceiveNftMsg::NewTrade {
+            target: "1".to_string(),
+            expires: None,
+        },
+    )
+}
+
+#[test]
+fn wanted_tokens_are_accepted_on_arrival() {
+    let mut suite = Suite::new();
+    suite.mint(TRADER, "a");
+    suite.mint(TRADER, "b");
+    let wanted = TradePreferencesMsg {
+        token_ids: vec![suite.nft_ref("a")],
+        auto_accept: true,
+        ..preferences()
+    };
+    list_wanting(&mut suite, wanted);
+
+    assert_error(
+        suite.trade(TRADER, "b", "1"),
+        ContractError::TradeNotWanted {},
+    );
+    suite.trade(TRADER, "a", "1").unwrap();
+    assert_eq!(suite.nft_owner("1"), TRADER);
+    assert_eq!(suite.nft_owner("a"), SELLER);
+}
+
+#[test]
+fn collection_filter_leaves_matching_trades_open() {
+    let mut suite = Suite::new();
+    let lizards = suite.lizards.clone();
+    suite.mint(TRADER, "a");
+    suite.mint_in(&lizards, TRADER, "a");
+    let wanted = TradePreferencesMsg {
+        collection: Some(lizards.to_string()),
+        auto_accept: true,
+        ..preferences()
+    };
+    list_wanting(&mut suite, wanted);
+
+    assert_error(
+        suite.trade(TRADER, "a", "1"),
+        ContractError::TradeNotWanted {},
+    );
+    // auto accept only applies to the listed token ids
+    trade_in(&mut suite, &lizards, "a").unwrap();
+    assert_eq!(suite.nft_owner("1"), suite.market.to_string());
+    assert_eq!(suite.nft_owner_in(&lizards, "a"), suite.market.to_string());
+}
+
+#[test]
+fn trait_filters_read_the_nft_metadata() {
+    let mut suite = Suite::new();
+    let (nft, lizards) = (suite.nft.clone(), suite.lizards.clone());
+    suite.mint_with(&nft, TRADER, "blue", Some(traits(&[("color", "blue")])));
+    suite.mint_with(
+        &nft,
+        TRADER,
+        "green",
+        Some(traits(&[("color", "green"), ("legs", "4")])),
+    );
+    suite.mint(TRADER, "plain");
+    let legs = |value| LizardMetadata {
+        attributes: vec![LizardTrait {
+            trait_type: "legs".to_string(),
+            value,
+        }],
+    };
+    suite.mint_with(&lizards, TRADER, "four", Some(legs(4)));
+    suite.mint_with(&lizards, TRADER, "six", Some(legs(6)));
+    list_wanting(
+        &mut suite,
+        TradePreferencesMsg {
+            traits: vec![filter("legs", "4")],
+            ..preferences()
+        },
+    );
+
+    assert_error(
+        suite.trade(TRADER, "blue", "1"),
+        ContractError::TradeNotWanted {},
+    );
+    assert_error(
+        suite.trade(TRADER, "plain", "1"),
+        ContractError::TradeNotWanted {},
+    );
+    suite.trade(TRADER, "green", "1").unwrap();
+
+    // numeric values are compared in their JSON spelling
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::CancelTrade {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_error(
+        trade_in(&mut suite, &lizards, "six"),
+        ContractError::TradeNotWanted {},
+    );
+    trade_in(&mut suite, &lizards, "four").unwrap();
+
+    // every filter has to match
+    suite
+        .execute(
+            SELLER,
+            &ExecuteMsg::SetTradePreferences {
+                id: "1".to_string(),
+                preferences: Some(TradePreferencesMsg {
+                    token_ids: vec![nft_ref(&nft, "blue")],
+                    traits: vec![filter("legs", "4"), filter("color", "green")],
+                    ..preferences()
+                }),
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute(
+            TRADER,
+            &ExecuteMsg::CancelTrade {
+                id: "1".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_error(
+        trade_in(&mut suite, &lizards, "four"),
+        ContractError::TradeNotWanted {},
+    );
+    suite.trade(TRADER, "green", "1").unwrap();
+}
+
+#[test]
+fn only_the_seller_changes_preferences() {
+    let mut suite = Suite::new();
+    suite.mint(TRADER, "a");
+    list_wanting(
+        &mut suite,
+        TradePreferencesMsg {
+            token_ids: vec![nft_ref(&Addr::unchecked("elsewhere"), "a")],
+            ..preferences()
+        },
+    );
+    assert_error(
+        suite.trade(TRADER, "a", "1"),
+        ContractError::TradeNotWanted {},
+    );
+
+    let clear = ExecuteMsg::SetTradePreferences {
+        id: "1".to_string(),
+        preferences: None,
+    };
+    assert_error(
+        suite.execute(TRADER, &clear, &[]),
+        ContractError::Unauthorized {},
+    );
+    suite.execute(SELLER, &clear, &[]).unwrap();
+    suite.trade(TRADER, "a", "1").unwrap();
+}